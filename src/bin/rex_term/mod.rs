@@ -34,6 +34,7 @@ impl RustBoxFrontend {
         match style {
             Style::Default => (RB_NORMAL, Color::Default, Color::Default),
             Style::Selection => (RB_REVERSE, Color::Default, Color::Default),
+            Style::Annotation => (RB_NORMAL, Color::Black, Color::Yellow),
             Style::Hint => (RB_UNDERLINE, Color::Default, Color::Default),
             Style::StatusBar => (RB_REVERSE, Color::Default, Color::Default),
             Style::InputLine => (RB_BOLD, Color::White, Color::Blue),
@@ -13,7 +13,11 @@ use docopt::Docopt;
 use gag::Hold;
 
 use rex::frontend::{Frontend, Event, KeyPress};
+use rex::filesystem::DefaultFilesystem;
 use rex::ui::view::HexEdit;
+use rex::util::buffer::Buffer;
+use rex::util::piece_table::PieceTable;
+use rex::util::split_vec::SplitVec;
 
 use rex_term::RustBoxFrontend;
 
@@ -23,12 +27,15 @@ Usage: rex [options] [FILE]
 
 Options:
     -h, --help                  Show this help message
+    -p, --piece-table           Keep the edits in a piece table, which makes large inserts,
+                                deletes and their undo cheap
 ";
 
 #[derive(RustcDecodable, Debug)]
 #[allow(non_snake_case)]
 struct Args {
     flag_help: bool,
+    flag_piece_table: bool,
     arg_FILE: Option<String>,
 }
 
@@ -43,7 +50,15 @@ fn main() {
         process::exit(0);
     }
 
-    let mut edit: HexEdit = HexEdit::new();
+    if args.flag_piece_table {
+        run::<PieceTable>(&args);
+    } else {
+        run::<SplitVec>(&args);
+    }
+}
+
+fn run<B: Buffer + 'static>(args: &Args) {
+    let mut edit: HexEdit<DefaultFilesystem, B> = HexEdit::new();
 
     if let Some(ref filename) = args.arg_FILE {
         edit.open(&Path::new(filename));
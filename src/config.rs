@@ -74,6 +74,10 @@ pub struct Config<FS: Filesystem+'static> {
     pub line_width: Option<u32>,
     pub group_bytes: i64,
     pub little_endian: bool,
+    pub persist_history: bool,
+    pub undo_memory_limit: i64,
+    pub saturating_increment: bool,
+    pub strings_min_len: i64,
 
     _fs: PhantomData<FS>
 }
@@ -86,6 +90,10 @@ impl<FS: Filesystem+'static> Default for Config<FS> {
             line_width: None,
             group_bytes: 1,
             little_endian: false,
+            persist_history: false,
+            undo_memory_limit: 256,
+            saturating_increment: false,
+            strings_min_len: 4,
             _fs: PhantomData,
         }
     }
@@ -178,6 +186,22 @@ impl<FS: Filesystem+'static> Config<FS> {
             }
         );
         decode_toml!(self, little_endian, t, Boolean);
+        decode_toml!(self, persist_history, t, Boolean);
+        decode_toml!(self, undo_memory_limit, t, Integer, |i|
+            if i >= 0 {
+                Ok(i)
+            } else {
+                Err(ConfigError::InvalidFieldValue("undo_memory_limit must be >= 0"))
+            }
+        );
+        decode_toml!(self, saturating_increment, t, Boolean);
+        decode_toml!(self, strings_min_len, t, Integer, |i|
+            if i >= 1 {
+                Ok(i)
+            } else {
+                Err(ConfigError::InvalidFieldValue("strings_min_len must be >= 1"))
+            }
+        );
         if let Some((key, _)) = t.into_iter().next() {
             Err(ConfigError::InvalidFieldName(key))
         } else {
@@ -191,6 +215,10 @@ impl<FS: Filesystem+'static> Config<FS> {
         create_toml!(self, p, line_width, Integer, |opt_i| if let Some(i) = opt_i { i as i64 } else { 0 });
         create_toml!(self, p, group_bytes, Integer);
         create_toml!(self, p, little_endian, Boolean);
+        create_toml!(self, p, persist_history, Boolean);
+        create_toml!(self, p, undo_memory_limit, Integer);
+        create_toml!(self, p, saturating_increment, Boolean);
+        create_toml!(self, p, strings_min_len, Integer);
         None
     }
 
@@ -7,23 +7,53 @@ use std::io::Write;
 use std::path::{PathBuf, Path};
 
 use xdg;
+use memmap::{Mmap, Protection};
 
 pub trait Filesystem {
     type FSRead: Read;
     type FSWrite: Write;
+    type FSMap: AsRef<[u8]> + 'static;
     fn open_config<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, config_name: P2) -> Option<PathBuf>;
     fn save_config<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, config_name: P2) -> io::Result<PathBuf>;
+    /// Find a data file previously placed with `save_data`, such as saved editing state.
+    fn open_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> Option<PathBuf>;
+    fn save_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> io::Result<PathBuf>;
     fn make_absolute<P: AsRef<Path>>(p: P) -> io::Result<PathBuf>;
     fn open<P: AsRef<Path>>(p: P) -> io::Result<Self::FSRead>;
+    /// Open a file for reading without copying its contents into memory.
+    fn open_mapped<P: AsRef<Path>>(p: P) -> io::Result<Self::FSMap>;
     fn can_open<P: AsRef<Path>>(p: P) -> io::Result<()>;
     fn save<P: AsRef<Path>>(p: P) -> io::Result<Self::FSWrite>;
     fn can_save<P: AsRef<Path>>(p: P) -> io::Result<()>;
+    fn rename<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> io::Result<()>;
+    fn remove<P: AsRef<Path>>(p: P) -> io::Result<()>;
+    /// Follow symbolic links to the file a path points to. A path that doesn't exist yet is given
+    /// back as is.
+    fn resolve_links<P: AsRef<Path>>(p: P) -> io::Result<PathBuf>;
+    /// Give a file the permissions of another one.
+    fn copy_permissions<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> io::Result<()>;
+}
+
+/// A read-only memory map of a file.
+pub struct MappedFile(Option<Mmap>);
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        match self.0 {
+            // The file is mapped read-only, the only way for the data to change under us is for
+            // another process to modify the file.
+            Some(ref mmap) => unsafe { mmap.as_slice() },
+            // Empty files can not be mapped
+            None => &[],
+        }
+    }
 }
 
 pub struct DefaultFilesystem;
 impl Filesystem for DefaultFilesystem {
     type FSRead = File;
     type FSWrite = File;
+    type FSMap = MappedFile;
 
     fn open_config<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, config_name: P2) -> Option<PathBuf> {
         xdg::BaseDirectories::with_prefix(prefix).unwrap().find_config_file(config_name)
@@ -33,6 +63,14 @@ impl Filesystem for DefaultFilesystem {
         xdg::BaseDirectories::with_prefix(prefix).unwrap().place_config_file(config_name)
     }
 
+    fn open_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> Option<PathBuf> {
+        xdg::BaseDirectories::with_prefix(prefix).unwrap().find_data_file(data_name)
+    }
+
+    fn save_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> io::Result<PathBuf> {
+        xdg::BaseDirectories::with_prefix(prefix).unwrap().place_data_file(data_name)
+    }
+
     fn make_absolute<P: AsRef<Path>>(p: P) -> io::Result<PathBuf> {
         let mut path = try!(env::current_dir());
         path.push(p);
@@ -43,6 +81,14 @@ impl Filesystem for DefaultFilesystem {
         File::open(p)
     }
 
+    fn open_mapped<P: AsRef<Path>>(p: P) -> io::Result<Self::FSMap> {
+        let f = try!(File::open(p));
+        if try!(f.metadata()).len() == 0 {
+            return Ok(MappedFile(None));
+        }
+        Mmap::open(&f, Protection::Read).map(|mmap| MappedFile(Some(mmap)))
+    }
+
     fn can_open<P: AsRef<Path>>(p: P) -> io::Result<()> {
         let path = try!(Self::make_absolute(p));
 
@@ -86,4 +132,24 @@ impl Filesystem for DefaultFilesystem {
             e @ _ => e.map(|_| ()),
         }
     }
+
+    fn rename<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove<P: AsRef<Path>>(p: P) -> io::Result<()> {
+        fs::remove_file(p)
+    }
+
+    fn resolve_links<P: AsRef<Path>>(p: P) -> io::Result<PathBuf> {
+        match fs::canonicalize(p.as_ref()) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(p.as_ref().into()),
+            res => res,
+        }
+    }
+
+    fn copy_permissions<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> io::Result<()> {
+        let permissions = try!(fs::metadata(from)).permissions();
+        fs::set_permissions(to, permissions)
+    }
 }
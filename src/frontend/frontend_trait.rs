@@ -2,6 +2,7 @@
 pub enum Style {
     Default,
     Selection,
+    Annotation,
     Hint,
     StatusBar,
     InputLine,
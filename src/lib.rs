@@ -6,6 +6,7 @@ extern crate toml;
 extern crate itertools;
 extern crate odds;
 extern crate xdg;
+extern crate memmap;
 #[macro_use] extern crate custom_derive;
 #[macro_use] extern crate newtype_derive;
 #[cfg(test)] pub mod bench;
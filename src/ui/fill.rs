@@ -0,0 +1,65 @@
+//! Generating the bytes to fill a range with.
+use std::iter;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The most bytes inserted by a fill, so a mistyped count doesn't exhaust the memory
+pub const MAX_FILL_LEN: usize = 256 * 1024 * 1024;
+
+/// What to fill a range with.
+#[derive(Debug, Clone)]
+pub enum Fill {
+    /// A pattern that is repeated over the range
+    Pattern(Vec<u8>),
+    Random,
+}
+
+impl Fill {
+    /// Get `len` bytes of the fill, the pattern is cut off at the end if needed.
+    pub fn bytes(&self, len: usize) -> Vec<u8> {
+        match *self {
+            Fill::Pattern(ref pattern) => pattern.iter().cloned().cycle().take(len).collect(),
+            Fill::Random => random_bytes(len),
+        }
+    }
+
+    /// Get `count` whole copies of the pattern, or `count` random bytes. Returns None if that is
+    /// more than `MAX_FILL_LEN` bytes.
+    pub fn copies(&self, count: usize) -> Option<Vec<u8>> {
+        let len = match *self {
+            Fill::Pattern(ref pattern) => pattern.len().checked_mul(count),
+            Fill::Random => Some(count),
+        };
+        match len {
+            Some(len) if len <= MAX_FILL_LEN => Some(self.bytes(len)),
+            _ => None,
+        }
+    }
+}
+
+/// Generate random bytes with xorshift64*, seeded from the clock. This is meant for padding and
+/// scrubbing data, not for anything that needs to be unpredictable.
+fn random_bytes(len: usize) -> Vec<u8> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::new(0, 0));
+    let mut state = (now.as_secs() ^ ((now.subsec_nanos() as u64) << 32)) | 1;
+    iter::repeat(()).take(len).map(|_| {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        (state.wrapping_mul(0x2545f4914f6cdd1d) >> 56) as u8
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fill_pattern() {
+        let fill = Fill::Pattern(vec![1, 2, 3]);
+        assert_eq!(fill.bytes(5), [1, 2, 3, 1, 2]);
+        assert_eq!(fill.copies(2), Some(vec![1, 2, 3, 1, 2, 3]));
+        assert_eq!(Fill::Random.copies(7).map(|bytes| bytes.len()), Some(7));
+        assert_eq!(fill.copies(MAX_FILL_LEN), None);
+        assert_eq!(fill.copies(!0), None);
+    }
+}
@@ -0,0 +1,74 @@
+//! Checksum fields embedded in the data, which can be recomputed after the data changes.
+use std::ops::Range;
+
+use util::buffer::Buffer;
+use util::checksum::{Algorithm, checksum};
+
+use super::marks::shift_offset;
+
+/// The definition of a checksum field: the data it covers, how it is computed and where it is.
+#[derive(Debug, Clone)]
+pub struct ChecksumFixup {
+    pub range: Range<usize>,
+    pub algorithm: Algorithm,
+    pub little_endian: bool,
+    pub target: usize,
+}
+
+impl ChecksumFixup {
+    /// Compute the value of the field from the data in the buffer, in the field's byte order.
+    pub fn compute<B: Buffer>(&self, buffer: &B) -> Result<Vec<u8>, String> {
+        if self.range.end > buffer.len() {
+            return Err("The checksummed range is past the end of the buffer".to_string());
+        }
+
+        let mut value = checksum(self.algorithm, buffer.iter_range_slices(self.range.clone()));
+        if self.target + value.len() > buffer.len() {
+            return Err("The checksum field is past the end of the buffer".to_string());
+        }
+        if self.little_endian {
+            value.reverse();
+        }
+        Ok(value)
+    }
+
+    /// Update the field after the range in the buffer was replaced by `new_len` bytes, so that
+    /// it keeps covering the same data.
+    pub fn splice(&mut self, range: &Range<usize>, new_len: usize) {
+        self.range = shift_offset(self.range.start, range, new_len)..
+                     shift_offset(self.range.end, range, new_len);
+        self.target = shift_offset(self.target, range, new_len);
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} {} of {}..{} at {}", self.algorithm.name(), if self.little_endian { "LE" } else { "BE" },
+            self.range.start, self.range.end, self.target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::buffer::Buffer;
+    use util::checksum::Algorithm;
+    use util::split_vec::SplitVec;
+
+    #[test]
+    fn test_fixup_follows_edits() {
+        let mut fixup = ChecksumFixup {
+            range: 10..20,
+            algorithm: Algorithm::Sum16,
+            little_endian: true,
+            target: 20,
+        };
+        fixup.splice(&(0..0), 5);
+        assert_eq!(fixup.range, 15..25);
+        assert_eq!(fixup.target, 25);
+
+        let buffer = SplitVec::from_vec(vec![1; 30]);
+        assert_eq!(fixup.compute(&buffer).unwrap(), [10, 0]);
+
+        fixup.target = 29;
+        assert!(fixup.compute(&buffer).is_err());
+    }
+}
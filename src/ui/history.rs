@@ -0,0 +1,521 @@
+use std::borrow::Cow;
+use std::cmp;
+use std::io;
+use std::io::{Read, Write};
+use std::iter;
+use std::ops::Range;
+use std::path::Path;
+
+use util::buffer::{Buffer, SavedData};
+
+/// Represents an edit operation done in a buffer, such as paste, insertion and deletion.
+/// Undo operations are also saved as EditOperations that revert the original operation, with the
+/// data in the form the buffer saved it in.
+#[derive(Debug, Clone)]
+pub struct EditOperation<D = Vec<u8>> {
+    /// The range over which the operation happens
+    pub range: Range<usize>,
+    /// The data that replaced the supplied range
+    pub data: D,
+    /// A short description of the type of operation for logging
+    pub description: Cow<'static, str>,
+}
+
+impl EditOperation {
+    pub fn delete(range: Range<usize>) -> EditOperation {
+        EditOperation {
+            range: range,
+            data : vec![],
+            description: "Delete".into(),
+        }
+    }
+
+    pub fn insert(offset: usize, data: Vec<u8>) -> EditOperation {
+        EditOperation {
+            range: offset..offset,
+            data: data,
+            description: "Insert".into(),
+        }
+    }
+
+    pub fn write(offset: usize, data: Vec<u8>) -> EditOperation {
+        EditOperation {
+            range: offset..(offset + data.len()),
+            data: data,
+            description: "Overwrite".into(),
+        }
+    }
+}
+
+const HISTORY_MAGIC: &'static [u8; 8] = b"rexhist1";
+
+/// Identifies the contents of a file, so that a saved history is only used for the contents it
+/// was saved for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HistoryKey {
+    path: String,
+    content_hash: u64,
+}
+
+impl HistoryKey {
+    pub fn new<'a, I: Iterator<Item=&'a [u8]>>(path: &Path, contents: I) -> HistoryKey {
+        HistoryKey {
+            path: path.to_string_lossy().into_owned(),
+            content_hash: hash_slices(contents),
+        }
+    }
+
+    /// The name of the data file the history for this key is saved in
+    pub fn file_name(&self) -> String {
+        format!("history/{:016x}", hash_slices(iter::once(self.path.as_bytes())))
+    }
+}
+
+/// A FNV-1a hash over some slices, unlike the std hasher it is stable so it can be saved.
+fn hash_slices<'a, I: Iterator<Item=&'a [u8]>>(slices: I) -> u64 {
+    slices.fold(0xcbf29ce484222325, |hash, slice|
+        slice.iter().fold(hash, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+    )
+}
+
+fn write_u64<W: Write>(w: &mut W, val: u64) -> io::Result<()> {
+    let mut buf = [0; 8];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (val >> (i * 8)) as u8;
+    }
+    w.write_all(&buf)
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    try!(r.read_exact(&mut buf));
+    Ok(buf.iter().rev().fold(0, |val, &b| (val << 8) | b as u64))
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    try!(write_u64(w, bytes.len() as u64));
+    w.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let len = try!(read_u64(r));
+    let mut bytes = vec![];
+    try!(r.by_ref().take(len).read_to_end(&mut bytes));
+    if bytes.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated history file"));
+    }
+    Ok(bytes)
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    String::from_utf8(try!(read_bytes(r))).map_err(|_|
+        io::Error::new(io::ErrorKind::InvalidData, "Invalid string in history file")
+    )
+}
+
+/// A single unit of undo, made of one or more operations that are reverted together.
+#[derive(Debug, Clone)]
+pub struct UndoGroup<D = Vec<u8>> {
+    /// The operations in the group, in the order they were done. They are applied in reverse.
+    pub operations: Vec<EditOperation<D>>,
+    /// A short description of the type of the group for logging
+    pub description: Cow<'static, str>,
+    /// The part of the buffer the edits in the group ended up in
+    range: Range<usize>,
+    /// The amount of bytes affected by the group
+    len: usize,
+    /// Whether new edits can still be merged into the group
+    open: bool,
+}
+
+impl<D: SavedData> UndoGroup<D> {
+    fn new(description: &'static str) -> UndoGroup<D> {
+        UndoGroup {
+            operations: vec![],
+            description: description.into(),
+            range: 0..0,
+            len: 0,
+            open: true,
+        }
+    }
+
+    fn from_operation(operation: EditOperation<D>) -> UndoGroup<D> {
+        UndoGroup {
+            description: operation.description.clone(),
+            range: operation.range.clone(),
+            len: cmp::max(operation.range.len(), operation.data.len()),
+            operations: vec![operation],
+            open: true,
+        }
+    }
+
+    /// Build the group that reverts this one from the operations produced while applying it.
+    pub fn reverted(&self, operations: Vec<EditOperation<D>>) -> UndoGroup<D> {
+        UndoGroup {
+            operations: operations,
+            description: self.description.clone(),
+            range: self.range.clone(),
+            len: self.len,
+            open: false,
+        }
+    }
+
+    /// Try to merge the (reverting) operation of an edit that continues the group, such as typing
+    /// the next byte. The operation is given back if it doesn't continue the group.
+    fn merge(&mut self, operation: EditOperation<D>) -> Result<(), EditOperation<D>> {
+        if !self.open {
+            return Err(operation);
+        }
+
+        let start = operation.range.start;
+        match (&*self.description, &*operation.description) {
+            // Typing forward
+            ("Insert", "Insert") if start == self.range.end => {
+                self.range.end = operation.range.end;
+                self.len += operation.range.len();
+            }
+            // Finishing a nibble, or retyping a byte, that was just inserted
+            ("Insert", "Overwrite") if start >= self.range.start &&
+                                       operation.range.end <= self.range.end => (),
+            ("Overwrite", "Overwrite") if start >= self.range.start && start <= self.range.end => {
+                self.range.end = cmp::max(self.range.end, operation.range.end);
+                self.len = self.range.len();
+            }
+            // Either deleting forward or backspacing
+            ("Delete", "Delete") if start == self.range.start ||
+                                    start + operation.data.len() == self.range.start => {
+                self.range = start..start;
+                self.len += operation.data.len();
+            }
+            _ => return Err(operation),
+        }
+
+        self.operations.push(operation);
+        Ok(())
+    }
+
+    fn write_to<W: Write, B: Buffer<Saved=D>>(&self, buffer: &B, w: &mut W) -> io::Result<()> {
+        try!(write_bytes(w, self.description.as_bytes()));
+        try!(write_u64(w, self.range.start as u64));
+        try!(write_u64(w, self.range.end as u64));
+        try!(write_u64(w, self.len as u64));
+        try!(write_u64(w, self.operations.len() as u64));
+        for operation in self.operations.iter() {
+            try!(write_u64(w, operation.range.start as u64));
+            try!(write_u64(w, operation.range.end as u64));
+            try!(write_bytes(w, operation.description.as_bytes()));
+            try!(write_bytes(w, &buffer.copy_saved(&operation.data)));
+        }
+        Ok(())
+    }
+
+    fn read_from<R: Read, B: Buffer<Saved=D>>(buffer: &mut B, r: &mut R) -> io::Result<UndoGroup<D>> {
+        let description = try!(read_string(r));
+        let range = try!(read_u64(r)) as usize..try!(read_u64(r)) as usize;
+        let len = try!(read_u64(r)) as usize;
+        let num_operations = try!(read_u64(r));
+        let mut operations = vec![];
+        for _ in 0..num_operations {
+            operations.push(EditOperation {
+                range: try!(read_u64(r)) as usize..try!(read_u64(r)) as usize,
+                description: try!(read_string(r)).into(),
+                data: buffer.save(try!(read_bytes(r))),
+            });
+        }
+
+        Ok(UndoGroup {
+            operations: operations,
+            description: description.into(),
+            range: range,
+            len: len,
+            open: false,
+        })
+    }
+
+    /// The amount of memory used by the data saved in the group
+    fn memory(&self) -> usize {
+        self.operations.iter().map(|operation| operation.data.memory()).sum()
+    }
+
+    /// Describe the group for the status bar, e.g. "insert 16 bytes"
+    pub fn describe(&self) -> String {
+        format!("{} {} byte{}", self.description.to_lowercase(), self.len,
+            if self.len == 1 { "" } else { "s" })
+    }
+}
+
+/// The undo and redo history of a buffer.
+///
+/// Consecutive edits of the same kind on adjacent positions are merged into a single undo group,
+/// so that typing a value nibble by nibble can be undone at once. Edits done between
+/// [begin_group](#method.begin_group) and [end_group](#method.end_group) always end up in the
+/// same group.
+///
+/// Since operations keep the data they replace, the history keeps track of the memory it uses, so
+/// that it can be limited with [trim](#method.trim).
+#[derive(Debug)]
+pub struct History<D = Vec<u8>> {
+    undo_stack: Vec<UndoGroup<D>>,
+    redo_stack: Vec<UndoGroup<D>>,
+    group_depth: usize,
+    memory: usize,
+    truncated: bool,
+}
+
+impl<D: SavedData> History<D> {
+    pub fn new() -> History<D> {
+        History {
+            undo_stack: vec![],
+            redo_stack: vec![],
+            group_depth: 0,
+            memory: 0,
+            truncated: false,
+        }
+    }
+
+    /// Record the operation reverting a new edit. Any undone groups can no longer be redone.
+    pub fn push(&mut self, operation: EditOperation<D>) {
+        let redo_memory: usize = self.redo_stack.iter().map(UndoGroup::memory).sum();
+        self.memory -= redo_memory;
+        self.redo_stack.clear();
+        self.memory += operation.data.memory();
+
+        let operation = match self.undo_stack.last_mut() {
+            Some(group) => {
+                if self.group_depth > 0 {
+                    group.len += cmp::max(operation.range.len(), operation.data.len());
+                    group.operations.push(operation);
+                    return;
+                }
+                match group.merge(operation) {
+                    Ok(()) => return,
+                    Err(operation) => {
+                        // The new group is undone first, after that nothing can continue this one
+                        group.open = false;
+                        operation
+                    }
+                }
+            }
+            None => operation,
+        };
+
+        self.undo_stack.push(UndoGroup::from_operation(operation));
+    }
+
+    /// Start a group that all following edits will be part of, until the matching `end_group`.
+    /// Groups can be nested, in which case they are all part of the outermost group.
+    pub fn begin_group(&mut self, description: &'static str) {
+        if self.group_depth == 0 {
+            if let Some(group) = self.undo_stack.last_mut() {
+                group.open = false;
+            }
+            self.undo_stack.push(UndoGroup::new(description));
+        }
+        self.group_depth += 1;
+    }
+
+    /// End a group started with `begin_group`.
+    pub fn end_group(&mut self) {
+        self.group_depth -= 1;
+        if self.group_depth > 0 {
+            return;
+        }
+
+        let is_empty = match self.undo_stack.last_mut() {
+            Some(group) => {
+                group.open = false;
+                group.operations.is_empty()
+            }
+            None => false,
+        };
+        if is_empty {
+            self.undo_stack.pop();
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<UndoGroup<D>> {
+        let group = self.undo_stack.pop();
+        self.memory -= group.as_ref().map_or(0, UndoGroup::memory);
+        group
+    }
+
+    pub fn pop_redo(&mut self) -> Option<UndoGroup<D>> {
+        let group = self.redo_stack.pop();
+        self.memory -= group.as_ref().map_or(0, UndoGroup::memory);
+        group
+    }
+
+    /// Push a group that reverts an undone group
+    pub fn push_redo(&mut self, group: UndoGroup<D>) {
+        self.memory += group.memory();
+        self.redo_stack.push(group);
+    }
+
+    /// Push a group that reverts a redone group, without affecting the redo stack
+    pub fn push_undo(&mut self, group: UndoGroup<D>) {
+        self.memory += group.memory();
+        self.undo_stack.push(group);
+    }
+
+    /// Drop the oldest groups until the history uses at most `limit` bytes of memory, first the
+    /// oldest undo groups and then the furthest redo groups. Returns the number of groups dropped.
+    ///
+    /// The newest undo group is always kept, so the last edit can be undone even if it is bigger
+    /// than the limit on its own.
+    pub fn trim(&mut self, limit: usize) -> usize {
+        let mut dropped = 0;
+        while self.memory > limit {
+            let group = if self.undo_stack.len() > 1 {
+                self.undo_stack.remove(0)
+            } else if !self.redo_stack.is_empty() {
+                self.redo_stack.remove(0)
+            } else {
+                break;
+            };
+            self.memory -= group.memory();
+            dropped += 1;
+        }
+
+        if dropped > 0 {
+            self.truncated = true;
+        }
+        dropped
+    }
+
+    /// The amount of memory used by the data saved in the history
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Returns true if groups were dropped from the history to save memory
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Save the history of the file contents identified by the key, the saved data of the
+    /// operations is copied out of the buffer that saved it.
+    pub fn save_to<W: Write, B: Buffer<Saved=D>>(&self, buffer: &B, key: &HistoryKey, w: &mut W)
+            -> io::Result<()> {
+        try!(w.write_all(HISTORY_MAGIC));
+        try!(write_bytes(w, key.path.as_bytes()));
+        try!(write_u64(w, key.content_hash));
+        for stack in [&self.undo_stack, &self.redo_stack].iter() {
+            try!(write_u64(w, stack.len() as u64));
+            for group in stack.iter() {
+                try!(group.write_to(buffer, w));
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a history saved with `save_to`, saving the data of the operations in the buffer. If it
+    /// was saved for a different file, or for different contents of the file, None is returned.
+    pub fn load_from<R: Read, B: Buffer<Saved=D>>(buffer: &mut B, key: &HistoryKey, r: &mut R)
+            -> io::Result<Option<History<D>>> {
+        let mut magic = [0; 8];
+        try!(r.read_exact(&mut magic));
+        if &magic != HISTORY_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid history file"));
+        }
+
+        let saved_key = HistoryKey {
+            path: try!(read_string(r)),
+            content_hash: try!(read_u64(r)),
+        };
+        if saved_key != *key {
+            return Ok(None);
+        }
+
+        let mut stacks = vec![];
+        for _ in 0..2 {
+            let num_groups = try!(read_u64(r));
+            let mut stack = vec![];
+            for _ in 0..num_groups {
+                stack.push(try!(UndoGroup::read_from(buffer, r)));
+            }
+            stacks.push(stack);
+        }
+
+        let redo_stack = stacks.pop().unwrap();
+        let undo_stack = stacks.pop().unwrap();
+        let memory = undo_stack.iter().chain(redo_stack.iter()).map(UndoGroup::memory).sum();
+        Ok(Some(History {
+            undo_stack: undo_stack,
+            redo_stack: redo_stack,
+            group_depth: 0,
+            memory: memory,
+            truncated: false,
+        }))
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo_stack.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history_with_writes(count: usize, size: usize) -> History {
+        let mut history = History::new();
+        for i in 0..count {
+            // Far apart, so that they are not merged into one group
+            history.push(EditOperation::write(i * size * 2, vec![0; size]));
+        }
+        history
+    }
+
+    #[test]
+    fn test_trim_drops_oldest() {
+        let mut history = history_with_writes(4, 10);
+        assert_eq!(history.memory(), 40);
+
+        assert_eq!(history.trim(25), 2);
+        assert_eq!(history.undo_len(), 2);
+        assert_eq!(history.memory(), 20);
+        assert!(history.is_truncated());
+
+        // The newest group is the one left to undo
+        let group = history.pop_undo().unwrap();
+        assert_eq!(group.operations[0].range, 60..70);
+    }
+
+    #[test]
+    fn test_trim_under_limit() {
+        let mut history = history_with_writes(4, 10);
+
+        assert_eq!(history.trim(40), 0);
+        assert_eq!(history.undo_len(), 4);
+        assert!(!history.is_truncated());
+    }
+
+    #[test]
+    fn test_trim_keeps_open_group() {
+        let mut history = history_with_writes(1, 10);
+        history.begin_group("Paste");
+        history.push(EditOperation::write(100, vec![0; 50]));
+
+        history.trim(20);
+        history.push(EditOperation::write(200, vec![0; 5]));
+        history.end_group();
+
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.pop_undo().unwrap().operations.len(), 2);
+    }
+
+    #[test]
+    fn test_trim_keeps_newest_group() {
+        let mut history = history_with_writes(2, 10);
+        history.push(EditOperation::write(100, vec![0; 50]));
+
+        assert_eq!(history.trim(20), 2);
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.memory(), 50);
+        assert_eq!(history.pop_undo().unwrap().operations[0].range, 100..150);
+    }
+}
@@ -4,6 +4,7 @@ use super::inputline::BaseInputLineActions;
 use super::overlay::OverlayActions;
 use super::menu::MenuActions;
 use super::configscreen::ConfigScreenActions;
+use super::picklist::PickListActions;
 
 pub struct Input;
 
@@ -26,14 +27,23 @@ impl Input {
             KeyPress::Tab => Some(HexEditActions::SwitchView),
             KeyPress::Insert => Some(HexEditActions::ToggleInsert),
             KeyPress::Shortcut(' ') => Some(HexEditActions::ToggleSelecion),
+            KeyPress::Shortcut('e') => Some(HexEditActions::ToggleInspector),
+            KeyPress::Shortcut('p') => Some(HexEditActions::ToggleMinimap),
             KeyPress::Shortcut('x') => Some(HexEditActions::CutSelection),
             KeyPress::Shortcut('c') => Some(HexEditActions::CopySelection),
             KeyPress::Shortcut('v') => Some(HexEditActions::PasteSelection),
             KeyPress::Shortcut('/') => Some(HexEditActions::HelpView),
             KeyPress::Shortcut('l') => Some(HexEditActions::LogView),
             KeyPress::Shortcut('z') => Some(HexEditActions::Undo),
+            KeyPress::Shortcut('y') => Some(HexEditActions::Redo),
             KeyPress::Shortcut('g') => Some(HexEditActions::AskGoto),
             KeyPress::Shortcut('f') => Some(HexEditActions::AskFind),
+            KeyPress::Shortcut('n') => Some(HexEditActions::FindNext),
+            KeyPress::Shortcut('b') => Some(HexEditActions::FindPrevious),
+            KeyPress::Shortcut('w') => Some(HexEditActions::AskWriteValue),
+            KeyPress::Shortcut('u') => Some(HexEditActions::StartCount),
+            KeyPress::Shortcut('a') => Some(HexEditActions::Increment),
+            KeyPress::Shortcut('d') => Some(HexEditActions::Decrement),
             KeyPress::Shortcut('o') => Some(HexEditActions::AskOpen),
             KeyPress::Shortcut('s') => Some(HexEditActions::AskSave),
             KeyPress::Shortcut('\\') => Some(HexEditActions::StartMenu),
@@ -78,6 +88,16 @@ impl Input {
         }
     }
 
+    pub fn picklist_input(&self, key: KeyPress) -> Option<PickListActions> {
+        match key {
+            KeyPress::Down => Some(PickListActions::Down),
+            KeyPress::Up => Some(PickListActions::Up),
+            KeyPress::Enter => Some(PickListActions::Select),
+            KeyPress::Esc => Some(PickListActions::Cancel),
+            _ => None
+        }
+    }
+
     pub fn menu_input(&self, key: KeyPress) -> Option<MenuActions> {
         match key {
             KeyPress::Backspace => Some(MenuActions::Back),
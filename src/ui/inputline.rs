@@ -5,12 +5,18 @@ use std::marker::PhantomData;
 use std::str::FromStr;
 
 use util;
+use util::regex::Regex;
 use util::rect::Rect;
 use super::super::frontend::{Frontend, Style, KeyPress};
 use super::super::filesystem::Filesystem;
 use super::super::config::Value;
 use super::input::Input;
 use super::widget::Widget;
+use super::values::{ValueType, ValueKind, parse_int, uint_to_bytes};
+use super::transform::{Transform, TransformKind};
+use super::fill::Fill;
+use super::search::Needle;
+use util::checksum::Algorithm;
 
 
 use super::common::Canceled;
@@ -59,6 +65,27 @@ impl<T:InputLineBehavior> InputLine<T> {
             data: initial_val,
         }
     }
+
+    /// The length in bytes of the UTF-8 character before the input position
+    fn prev_char_len(&self) -> isize {
+        let mut pos = self.input_pos as usize;
+        while pos > 0 {
+            pos -= 1;
+            if self.data[pos] & 0xc0 != 0x80 {
+                break;
+            }
+        }
+        self.input_pos - pos as isize
+    }
+
+    /// The length in bytes of the UTF-8 character at the input position
+    fn next_char_len(&self) -> isize {
+        let mut pos = self.input_pos as usize + 1;
+        while pos < self.data.len() && self.data[pos] & 0xc0 == 0x80 {
+            pos += 1;
+        }
+        pos as isize - self.input_pos
+    }
 }
 
 impl<T:InputLineBehavior> Widget for InputLine<T> {
@@ -76,34 +103,36 @@ impl<T:InputLineBehavior> Widget for InputLine<T> {
             }
             BaseInputLineActions::MoveLeft => {
                 if self.input_pos > 0 {
-                    self.input_pos -= 1;
+                    self.input_pos -= self.prev_char_len();
                 }
             }
             BaseInputLineActions::MoveRight => {
                 if self.input_pos < self.data.len() as isize {
-                    self.input_pos += 1;
+                    self.input_pos += self.next_char_len();
                 }
             }
             BaseInputLineActions::Edit(ch) => {
-                if ch.len_utf8() == 1 {
-                    self.data.insert(self.input_pos as usize, ch as u8);
-                    self.input_pos += 1;
-                } else {
-                    // TODO: Make it printable rather than alphanumeric
+                let mut bytes = [0; 4];
+                let len = ch.encode_utf8(&mut bytes).len();
+                for (i, &b) in bytes[..len].iter().enumerate() {
+                    self.data.insert(self.input_pos as usize + i, b);
                 }
+                self.input_pos += len as isize;
             }
             BaseInputLineActions::Ctrl(ch) => {
                 self.behavior.do_shortcut(ch)
             }
             BaseInputLineActions::Delete => {
                 if self.input_pos < self.data.len() as isize {
-                    self.data.remove(self.input_pos as usize);
+                    let end = self.input_pos + self.next_char_len();
+                    self.data.drain(self.input_pos as usize..end as usize);
                 }
             }
             BaseInputLineActions::DeleteWithMove => {
                 if self.input_pos > 0 {
-                    self.input_pos -= 1;
-                    self.data.remove(self.input_pos as usize);
+                    let start = self.input_pos - self.prev_char_len();
+                    self.data.drain(start as usize..self.input_pos as usize);
+                    self.input_pos = start;
                     self.behavior.do_update(&self.data);
                 }
             }
@@ -135,7 +164,8 @@ impl<T:InputLineBehavior> Widget for InputLine<T> {
         rb.print_style(area.left as usize, area.top as usize, style,
                  &format!("{}{} ", prefix, str::from_utf8(&self.data).unwrap()));
         if has_focus {
-            rb.set_cursor(prefix.len() as isize + self.input_pos, (area.top as isize));
+            let input_chars = str::from_utf8(&self.data[..self.input_pos as usize]).unwrap().chars().count();
+            rb.set_cursor((prefix.len() + input_chars) as isize, (area.top as isize));
         }
     }
 }
@@ -241,46 +271,424 @@ impl InputLineBehavior for GotoInputLineBehavior {
     }
 }
 
+signal_decl!{WriteValueEvent(Vec<u8>)}
+
+/// An input line for a value to write at the cursor, with shortcuts to switch its type.
+pub struct WriteValueInputLine {
+    value_type: ValueType,
+    prefix: String,
+    err: Option<String>,
+    pub on_done: WriteValueEvent,
+    pub on_cancel: Canceled,
+}
+
+impl WriteValueInputLine {
+    pub fn new(value_type: ValueType) -> WriteValueInputLine {
+        WriteValueInputLine {
+            value_type: value_type,
+            prefix: format!("Write ({}): ", value_type),
+            err: None,
+            on_done: Default::default(),
+            on_cancel: Default::default(),
+        }
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.value_type.parse(str::from_utf8(data).unwrap())
+    }
+}
+
+impl InputLineBehavior for WriteValueInputLine {
+    fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn get_status(&self) -> Result<&str, &str> {
+        if let Some(ref s) = self.err {
+            Err(s)
+        } else {
+            Ok("")
+        }
+    }
+
+    fn do_update(&mut self, data: &[u8]) {
+        self.err = if data.is_empty() { None } else { self.parse(data).err() };
+    }
+
+    fn do_enter(&mut self, data: &[u8]) {
+        match self.parse(data) {
+            Ok(bytes) => self.on_done.signal(bytes),
+            Err(e) => self.err = Some(e),
+        }
+    }
+
+    fn do_cancel(&mut self) {
+        self.on_cancel.signal(None);
+    }
+
+    fn do_shortcut(&mut self, shortcut: char) {
+        match shortcut {
+            'b' => self.value_type.set_size(1),
+            'w' => self.value_type.set_size(2),
+            'd' => self.value_type.set_size(4),
+            'q' => self.value_type.set_size(8),
+            'u' => self.value_type.set_kind(ValueKind::Unsigned),
+            's' => self.value_type.set_kind(ValueKind::Signed),
+            'f' => self.value_type.set_kind(ValueKind::Float),
+            'e' => self.value_type.little_endian = !self.value_type.little_endian,
+            _ => ()
+        }
+        self.prefix = format!("Write ({}): ", self.value_type);
+    }
+}
+
+signal_decl!{TransformEvent(Transform)}
+
+/// An input line for the argument of a transform of the selection. Transforms on words have
+/// shortcuts to switch the word size and byte order.
+pub struct TransformInputLine {
+    kind: TransformKind,
+    word_type: ValueType,
+    prefix: String,
+    err: Option<String>,
+    pub on_done: TransformEvent,
+    pub on_cancel: Canceled,
+}
+
+impl TransformInputLine {
+    pub fn new(kind: TransformKind, word_type: ValueType) -> TransformInputLine {
+        TransformInputLine {
+            kind: kind,
+            word_type: word_type,
+            prefix: TransformInputLine::make_prefix(kind, word_type),
+            err: None,
+            on_done: Default::default(),
+            on_cancel: Default::default(),
+        }
+    }
+
+    fn make_prefix(kind: TransformKind, word_type: ValueType) -> String {
+        match kind {
+            TransformKind::Xor | TransformKind::And | TransformKind::Or =>
+                format!("{} key (Hex): ", kind.name()),
+            TransformKind::Add | TransformKind::Subtract =>
+                format!("{} ({}): ", kind.name(), word_type),
+            _ => format!("{} bits: ", kind.name()),
+        }
+    }
+}
+
+impl InputLineBehavior for TransformInputLine {
+    fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn get_status(&self) -> Result<&str, &str> {
+        if let Some(ref s) = self.err {
+            Err(s)
+        } else {
+            Ok("")
+        }
+    }
+
+    fn do_update(&mut self, data: &[u8]) {
+        self.err = if data.is_empty() {
+            None
+        } else {
+            self.kind.parse(str::from_utf8(data).unwrap(), self.word_type).err()
+        };
+    }
+
+    fn do_enter(&mut self, data: &[u8]) {
+        match self.kind.parse(str::from_utf8(data).unwrap(), self.word_type) {
+            Ok(transform) => self.on_done.signal(transform),
+            Err(e) => self.err = Some(e),
+        }
+    }
+
+    fn do_cancel(&mut self) {
+        self.on_cancel.signal(None);
+    }
+
+    fn do_shortcut(&mut self, shortcut: char) {
+        if !self.kind.uses_words() {
+            return;
+        }
+        match shortcut {
+            'b' => self.word_type.set_size(1),
+            'w' => self.word_type.set_size(2),
+            'd' => self.word_type.set_size(4),
+            'q' => self.word_type.set_size(8),
+            'e' => self.word_type.little_endian = !self.word_type.little_endian,
+            _ => ()
+        }
+        self.prefix = TransformInputLine::make_prefix(self.kind, self.word_type);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum FillType {
+    Hex,
+    Ascii,
+    Random,
+}
+
+signal_decl!{FillEvent(Fill)}
+
+pub struct FillInputLine {
+    fill_type: FillType,
+    is_valid: bool,
+    pub on_done: FillEvent,
+    pub on_cancel: Canceled,
+}
+
+impl FillInputLine {
+    pub fn new() -> FillInputLine {
+        FillInputLine {
+            fill_type: FillType::Hex,
+            is_valid: false,
+            on_done: Default::default(),
+            on_cancel: Default::default(),
+        }
+    }
+
+    fn get_fill(&self, data: &[u8]) -> Option<Fill> {
+        match self.fill_type {
+            FillType::Hex => {
+                let hex: String = str::from_utf8(data).unwrap().split_whitespace().collect();
+                hex.from_hex().ok().map(Fill::Pattern)
+            }
+            FillType::Ascii => Some(Fill::Pattern(data.to_vec())),
+            FillType::Random => Some(Fill::Random),
+        }.and_then(|fill| match fill {
+            Fill::Pattern(ref pattern) if pattern.is_empty() => None,
+            fill => Some(fill),
+        })
+    }
+}
+
+impl InputLineBehavior for FillInputLine {
+    fn get_prefix(&self) -> &str {
+        match self.fill_type {
+            FillType::Hex => "Fill(Hex): ",
+            FillType::Ascii => "Fill(Ascii): ",
+            FillType::Random => "Fill(Random): ",
+        }
+    }
+
+    fn get_status(&self) -> Result<&str, &str> {
+        if self.is_valid {
+            Ok("")
+        } else if self.fill_type == FillType::Hex {
+            Err("Invalid Hex Value")
+        } else {
+            Err("Empty pattern")
+        }
+    }
+
+    fn do_update(&mut self, data: &[u8]) {
+        self.is_valid = self.get_fill(data).is_some();
+    }
+
+    fn do_enter(&mut self, data: &[u8]) {
+        if let Some(fill) = self.get_fill(data) {
+            self.on_done.signal(fill);
+        }
+    }
+
+    fn do_cancel(&mut self) {
+        self.on_cancel.signal(None);
+    }
+
+    fn do_shortcut(&mut self, shortcut: char) {
+        match shortcut {
+            'h' => self.fill_type = FillType::Hex,
+            'a' => self.fill_type = FillType::Ascii,
+            'r' => self.fill_type = FillType::Random,
+            _ => ()
+        }
+    }
+}
+
+signal_decl!{ChecksumFieldEvent(Algorithm, bool, usize)}
+
+/// An input line for the offset of a checksum field, with shortcuts to switch the algorithm and
+/// the byte order of the field.
+pub struct ChecksumFixupInputLine {
+    algorithm: Algorithm,
+    little_endian: bool,
+    prefix: String,
+    is_valid: bool,
+    pub on_done: ChecksumFieldEvent,
+    pub on_cancel: Canceled,
+}
+
+impl ChecksumFixupInputLine {
+    pub fn new(little_endian: bool) -> ChecksumFixupInputLine {
+        let algorithm = Algorithm::fields()[0];
+        ChecksumFixupInputLine {
+            algorithm: algorithm,
+            little_endian: little_endian,
+            prefix: ChecksumFixupInputLine::make_prefix(algorithm, little_endian),
+            is_valid: false,
+            on_done: Default::default(),
+            on_cancel: Default::default(),
+        }
+    }
+
+    fn make_prefix(algorithm: Algorithm, little_endian: bool) -> String {
+        format!("Fix-up {} {} at: ", algorithm.name(), if little_endian { "LE" } else { "BE" })
+    }
+
+    fn parse(data: &[u8]) -> Option<usize> {
+        match parse_int(str::from_utf8(data).unwrap().trim()) {
+            Some((false, offset)) => Some(offset as usize),
+            _ => None,
+        }
+    }
+}
+
+impl InputLineBehavior for ChecksumFixupInputLine {
+    fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn get_status(&self) -> Result<&str, &str> {
+        if self.is_valid {
+            Ok("")
+        } else {
+            Err("Invalid offset")
+        }
+    }
+
+    fn do_update(&mut self, data: &[u8]) {
+        self.is_valid = ChecksumFixupInputLine::parse(data).is_some();
+    }
+
+    fn do_enter(&mut self, data: &[u8]) {
+        if let Some(offset) = ChecksumFixupInputLine::parse(data) {
+            self.on_done.signal(self.algorithm, self.little_endian, offset);
+        }
+    }
+
+    fn do_cancel(&mut self) {
+        self.on_cancel.signal(None);
+    }
+
+    fn do_shortcut(&mut self, shortcut: char) {
+        match shortcut {
+            'n' => {
+                let fields = Algorithm::fields();
+                let index = fields.iter().position(|&a| a == self.algorithm).unwrap();
+                self.algorithm = fields[(index + 1) % fields.len()];
+            }
+            'e' => self.little_endian = !self.little_endian,
+            _ => ()
+        }
+        self.prefix = ChecksumFixupInputLine::make_prefix(self.algorithm, self.little_endian);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum UnicodeEncoding {
+    Utf16,
+    Utf32,
+    Utf8,
+}
+
+impl UnicodeEncoding {
+    fn next(&self) -> UnicodeEncoding {
+        match *self {
+            UnicodeEncoding::Utf16 => UnicodeEncoding::Utf32,
+            UnicodeEncoding::Utf32 => UnicodeEncoding::Utf8,
+            UnicodeEncoding::Utf8 => UnicodeEncoding::Utf16,
+        }
+    }
+
+    fn name(&self, little_endian: bool) -> String {
+        let endian = if little_endian { "LE" } else { "BE" };
+        match *self {
+            UnicodeEncoding::Utf16 => format!("UTF-16{}", endian),
+            UnicodeEncoding::Utf32 => format!("UTF-32{}", endian),
+            UnicodeEncoding::Utf8 => "UTF-8".to_string(),
+        }
+    }
+
+    fn encode(&self, s: &str, little_endian: bool) -> Vec<u8> {
+        let units: Vec<u32> = match *self {
+            UnicodeEncoding::Utf16 => s.encode_utf16().map(|u| u as u32).collect(),
+            UnicodeEncoding::Utf32 => s.chars().map(|c| c as u32).collect(),
+            UnicodeEncoding::Utf8 => return s.as_bytes().to_vec(),
+        };
+        let size = if *self == UnicodeEncoding::Utf16 { 2 } else { 4 };
+        units.into_iter().flat_map(|u| uint_to_bytes(u as u64, size, little_endian)).collect()
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum DataType {
     AsciiStr,
-    UnicodeStr,
+    UnicodeStr(UnicodeEncoding),
     HexStr,
+    Regex,
+    Value,
 }
 
-signal_decl!{FindEvent(Vec<u8>)}
+signal_decl!{FindEvent(Needle)}
 
 pub struct FindInputLine {
     data_type: DataType,
-    is_valid: bool,
+    /// The byte order of UTF-16 and UTF-32 text
+    little_endian: bool,
+    /// The type of the values searched for, it has its own byte order
+    value_type: ValueType,
+    prefix: String,
+    err: Option<String>,
     pub on_find: FindEvent,
     pub on_cancel: Canceled,
 }
 
 impl FindInputLine {
-    pub fn new() -> FindInputLine {
-        FindInputLine {
+    pub fn new(value_type: ValueType) -> FindInputLine {
+        let mut find_line = FindInputLine {
             data_type: DataType::AsciiStr,
-            is_valid: true,
+            little_endian: true,
+            value_type: value_type,
+            prefix: String::new(),
+            err: None,
             on_find: Default::default(),
             on_cancel: Default::default(),
-        }
+        };
+        find_line.update_prefix();
+        find_line
     }
 
     fn set_search_data_type(&mut self, dt: DataType) {
         self.data_type = dt;
+        self.update_prefix();
+    }
+
+    fn update_prefix(&mut self) {
+        self.prefix = match self.data_type {
+            DataType::AsciiStr => "Find(Ascii): ".to_string(),
+            DataType::UnicodeStr(encoding) => format!("Find({}): ", encoding.name(self.little_endian)),
+            DataType::HexStr => "Find(Hex): ".to_string(),
+            DataType::Regex => "Find(Regex): ".to_string(),
+            DataType::Value => format!("Find({}): ", self.value_type),
+        };
     }
 
-    fn parse_hex(&self, data: &[u8]) -> Option<Vec<u8>> {
-        str::from_utf8(data).unwrap().from_hex().ok()
+    fn parse_hex(&self, data: &[u8]) -> Option<Needle> {
+        Needle::parse_hex(str::from_utf8(data).unwrap())
     }
 
     fn do_find(&mut self, data: &[u8]) {
         let ll = self.parse_hex(data);
 
-        let needle: Vec<u8> = match self.data_type {
-            DataType::AsciiStr => data.clone().into(),
-            DataType::UnicodeStr => data.clone().into(),
+        let needle = match self.data_type {
+            DataType::AsciiStr => Needle::Bytes(data.clone().into()),
+            DataType::UnicodeStr(encoding) =>
+                Needle::Bytes(encoding.encode(str::from_utf8(data).unwrap(), self.little_endian)),
             DataType::HexStr => {
                 match ll {
                     Some(n) => n,
@@ -290,6 +698,24 @@ impl FindInputLine {
                     }
                 }
             }
+            DataType::Regex => {
+                match Regex::new(str::from_utf8(data).unwrap()) {
+                    Ok(regex) => Needle::Regex(regex),
+                    Err(e) => {
+                        self.err = Some(e);
+                        return;
+                    }
+                }
+            }
+            DataType::Value => {
+                match Needle::parse_value(str::from_utf8(data).unwrap(), self.value_type) {
+                    Ok(needle) => needle,
+                    Err(e) => {
+                        self.err = Some(e);
+                        return;
+                    }
+                }
+            }
         };
 
         self.on_find.signal(needle);
@@ -298,24 +724,26 @@ impl FindInputLine {
 
 impl InputLineBehavior for FindInputLine {
     fn get_prefix(&self) -> &str {
-        match self.data_type {
-            DataType::AsciiStr => "Find(Ascii): ",
-            DataType::UnicodeStr => "Find(Uni): ",
-            DataType::HexStr => "Find(Hex): ",
-        }
+        &self.prefix
     }
 
     fn get_status(&self) -> Result<&str, &str> {
-        if self.is_valid {
-            Ok("")
+        if let Some(ref s) = self.err {
+            Err(s)
         } else {
-            Err("Invalid Hex Value")
+            Ok("")
         }
     }
 
 
     fn do_update(&mut self, data: &[u8]) {
-        self.is_valid = (self.data_type != DataType::HexStr) || self.parse_hex(data).is_some();
+        self.err = match self.data_type {
+            DataType::HexStr if self.parse_hex(data).is_none() => Some("Invalid Hex Value".to_string()),
+            DataType::Regex => Regex::new(str::from_utf8(data).unwrap()).err(),
+            DataType::Value if !data.is_empty() =>
+                Needle::parse_value(str::from_utf8(data).unwrap(), self.value_type).err(),
+            _ => None,
+        };
     }
 
     fn do_enter(&mut self, data: &[u8]) {
@@ -332,11 +760,44 @@ impl InputLineBehavior for FindInputLine {
                 self.set_search_data_type(DataType::AsciiStr);
             }
             'u' => {
-                self.set_search_data_type(DataType::UnicodeStr);
+                // Pressing it again switches between the unicode encodings
+                let encoding = match self.data_type {
+                    DataType::UnicodeStr(encoding) => encoding.next(),
+                    _ => UnicodeEncoding::Utf16,
+                };
+                self.set_search_data_type(DataType::UnicodeStr(encoding));
+            }
+            'e' if self.data_type == DataType::Value => {
+                self.value_type.little_endian = !self.value_type.little_endian;
+                self.update_prefix();
+            }
+            'e' => {
+                self.little_endian = !self.little_endian;
+                self.update_prefix();
             }
             'h' => {
                 self.set_search_data_type(DataType::HexStr);
             }
+            'r' => {
+                self.set_search_data_type(DataType::Regex);
+            }
+            'n' => {
+                // Pressing it again switches between unsigned, signed and float values
+                if self.data_type == DataType::Value {
+                    let kind = match self.value_type.kind {
+                        ValueKind::Unsigned => ValueKind::Signed,
+                        ValueKind::Signed => ValueKind::Float,
+                        ValueKind::Float => ValueKind::Unsigned,
+                    };
+                    self.value_type.set_kind(kind);
+                }
+                self.set_search_data_type(DataType::Value);
+            }
+            'b' | 'w' | 'd' | 'q' if self.data_type == DataType::Value => {
+                let size = match shortcut { 'b' => 1, 'w' => 2, 'd' => 4, _ => 8 };
+                self.value_type.set_size(size);
+                self.update_prefix();
+            }
             _ => ()
         }
     }
@@ -460,3 +921,65 @@ impl InputLineBehavior for ConfigSetLine {
         self.on_cancel.signal(None);
     }
 }
+
+signal_decl!{TextEvent(String)}
+
+/// An input line for free text, such as names and comments. Control characters other than tabs
+/// are refused, since the text is saved in files that are meant to be readable.
+pub struct TextInputLine {
+    pub on_done: TextEvent,
+    pub on_cancel: Canceled,
+    prefix: &'static str,
+    allow_empty: bool,
+    err: Option<&'static str>,
+}
+
+impl TextInputLine {
+    pub fn new(prefix: &'static str, allow_empty: bool) -> TextInputLine {
+        TextInputLine {
+            on_done: Default::default(),
+            on_cancel: Default::default(),
+            prefix: prefix,
+            allow_empty: allow_empty,
+            err: if allow_empty { None } else { Some("Empty value") },
+        }
+    }
+
+    fn check(&self, data: &[u8]) -> Option<&'static str> {
+        let text = str::from_utf8(data).unwrap();
+        if text.chars().any(|c| c.is_control() && c != '\t') {
+            Some("Control characters aren't allowed")
+        } else if !self.allow_empty && text.trim().len() == 0 {
+            Some("Empty value")
+        } else {
+            None
+        }
+    }
+}
+
+impl InputLineBehavior for TextInputLine {
+    fn get_prefix(&self) -> &str {
+        self.prefix
+    }
+
+    fn get_status(&self) -> Result<&str, &str> {
+        match self.err {
+            Some(err) => Err(err),
+            None => Ok(""),
+        }
+    }
+
+    fn do_update(&mut self, data: &[u8]) {
+        self.err = self.check(data);
+    }
+
+    fn do_enter(&mut self, data: &[u8]) {
+        if self.err.is_none() {
+            self.on_done.signal(str::from_utf8(data).unwrap().trim().to_owned());
+        }
+    }
+
+    fn do_cancel(&mut self) {
+        self.on_cancel.signal(None);
+    }
+}
@@ -0,0 +1,152 @@
+//! Decoding of the bytes under the cursor as different data types.
+use std::mem;
+
+use super::values::{read_uint, to_signed};
+
+/// The number of bytes the inspector needs to decode all types.
+pub const INSPECTOR_DATA_LEN: usize = 16;
+
+/// The seconds between the FILETIME epoch (1601-01-01) and the unix epoch (1970-01-01)
+const FILETIME_UNIX_DIFF: i64 = 11644473600;
+
+/// Decode an unsigned LEB128 varint, returning the value and the number of bytes it used.
+pub fn read_uleb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut val = 0u64;
+    for (i, &b) in data.iter().enumerate().take(10) {
+        let shift = i * 7;
+        let bits = (b & 0x7f) as u64;
+        if shift == 63 && bits > 1 {
+            return None;
+        }
+        val |= bits << shift;
+        if b & 0x80 == 0 {
+            return Some((val, i + 1));
+        }
+    }
+    None
+}
+
+/// Decode a signed LEB128 varint, returning the value and the number of bytes it used.
+pub fn read_sleb128(data: &[u8]) -> Option<(i64, usize)> {
+    let mut val = 0i64;
+    for (i, &b) in data.iter().enumerate().take(10) {
+        let shift = i * 7;
+        val |= ((b & 0x7f) as i64) << shift;
+        if b & 0x80 == 0 {
+            if shift + 7 < 64 && b & 0x40 != 0 {
+                val |= -1 << (shift + 7);
+            }
+            return Some((val, i + 1));
+        }
+    }
+    None
+}
+
+/// Format seconds since the unix epoch as a UTC date, as long as the year has 4 digits.
+pub fn format_unix_time(secs: i64) -> Option<String> {
+    let days = if secs >= 0 { secs / 86400 } else { (secs - 86399) / 86400 };
+    let secs_of_day = secs - days * 86400;
+
+    // Convert the days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    if year < 0 || year > 9999 {
+        return None;
+    }
+
+    Some(format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day,
+        secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60))
+}
+
+/// Format a Windows FILETIME, the number of 100ns intervals since 1601-01-01, as a UTC date.
+pub fn format_filetime(filetime: u64) -> Option<String> {
+    format_unix_time((filetime / 10000000) as i64 - FILETIME_UNIX_DIFF)
+}
+
+fn format_float(val: f64) -> String {
+    let abs = val.abs();
+    if abs != 0.0 && (abs < 1e-4 || abs >= 1e15) {
+        format!("{:e}", val)
+    } else {
+        format!("{}", val)
+    }
+}
+
+fn or_missing(val: Option<String>) -> String {
+    val.unwrap_or("-".to_string())
+}
+
+fn endian_lines(data: &[u8], little_endian: bool, lines: &mut Vec<(&'static str, String)>) {
+    let uint = |size| read_uint(data, size, little_endian);
+
+    lines.push((if little_endian { "Little endian" } else { "Big endian" }, String::new()));
+    lines.push(("u16", or_missing(uint(2).map(|v| format!("{}", v)))));
+    lines.push(("i16", or_missing(uint(2).map(|v| format!("{}", to_signed(v, 2))))));
+    lines.push(("u32", or_missing(uint(4).map(|v| format!("{}", v)))));
+    lines.push(("i32", or_missing(uint(4).map(|v| format!("{}", to_signed(v, 4))))));
+    lines.push(("u64", or_missing(uint(8).map(|v| format!("{}", v)))));
+    lines.push(("i64", or_missing(uint(8).map(|v| format!("{}", v as i64)))));
+    lines.push(("f32", or_missing(uint(4).map(|v|
+        format_float(unsafe { mem::transmute::<u32, f32>(v as u32) } as f64)))));
+    lines.push(("f64", or_missing(uint(8).map(|v|
+        format_float(unsafe { mem::transmute::<u64, f64>(v) })))));
+    lines.push(("time_t", or_missing(uint(4).and_then(|v| format_unix_time(v as i64)))));
+    lines.push(("FILETIME", or_missing(uint(8).and_then(format_filetime))));
+}
+
+/// Decode the data as the different types the inspector shows, returning the lines as a type
+/// name and a value. Types with an endianness are shown for both, starting with the given one.
+pub fn inspect(data: &[u8], little_endian: bool) -> Vec<(&'static str, String)> {
+    let mut lines = vec![];
+
+    lines.push(("u8", or_missing(data.first().map(|v| format!("{}", v)))));
+    lines.push(("i8", or_missing(data.first().map(|v| format!("{}", *v as i8)))));
+    lines.push(("uleb128", or_missing(read_uleb128(data).map(|(v, _)| format!("{}", v)))));
+    lines.push(("sleb128", or_missing(read_sleb128(data).map(|(v, _)| format!("{}", v)))));
+    lines.push(("", String::new()));
+    endian_lines(data, little_endian, &mut lines);
+    lines.push(("", String::new()));
+    endian_lines(data, !little_endian, &mut lines);
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_leb128() {
+        assert_eq!(read_uleb128(&[0xe5, 0x8e, 0x26]), Some((624485, 3)));
+        assert_eq!(read_sleb128(&[0xc0, 0xbb, 0x78]), Some((-123456, 3)));
+        assert_eq!(read_sleb128(&[0x7f]), Some((-1, 1)));
+        assert_eq!(read_uleb128(&[0x80, 0x80]), None);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(format_unix_time(0).unwrap(), "1970-01-01 00:00:00");
+        assert_eq!(format_unix_time(951782400).unwrap(), "2000-02-29 00:00:00");
+        assert_eq!(format_unix_time(-1).unwrap(), "1969-12-31 23:59:59");
+        assert_eq!(format_filetime(116444736000000000).unwrap(), "1970-01-01 00:00:00");
+    }
+
+    #[test]
+    fn test_inspect_lines() {
+        let lines = inspect(&[0xff, 0xff], false);
+        assert_eq!(lines[0], ("u8", "255".to_string()));
+        assert_eq!(lines[1], ("i8", "-1".to_string()));
+        assert_eq!(lines[5].0, "Big endian");
+        assert_eq!(lines[7], ("i16", "-1".to_string()));
+        assert_eq!(lines[8], ("u32", "-".to_string()));
+    }
+}
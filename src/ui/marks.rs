@@ -0,0 +1,308 @@
+use std::cmp;
+use std::io;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+use toml;
+
+/// Move an offset into the buffer to where its data ended up after the range was replaced by
+/// `new_len` bytes. Offsets inside a removed part of the range end up at its start.
+pub fn shift_offset(pos: usize, range: &Range<usize>, new_len: usize) -> usize {
+    if pos >= range.end {
+        pos - (range.end - range.start) + new_len
+    } else if pos >= range.start {
+        range.start + cmp::min(pos - range.start, new_len)
+    } else {
+        pos
+    }
+}
+
+/// A named position in the buffer
+#[derive(Debug, Clone)]
+pub struct Mark {
+    pub name: String,
+    pub pos: usize,
+}
+
+/// The named marks of a buffer, kept sorted by their position.
+#[derive(Debug)]
+pub struct Marks {
+    marks: Vec<Mark>,
+}
+
+impl Marks {
+    pub fn new() -> Marks {
+        Marks {
+            marks: vec![],
+        }
+    }
+
+    /// Set a mark, moving it if a mark with the same name already exists. Returns true if the
+    /// mark was moved.
+    pub fn set(&mut self, name: String, pos: usize) -> bool {
+        let existed = self.remove(&name).is_some();
+        let index = match self.marks.binary_search_by(|mark| mark.pos.cmp(&pos)) {
+            Ok(index) | Err(index) => index,
+        };
+        self.marks.insert(index, Mark { name: name, pos: pos });
+        existed
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Mark> {
+        self.marks.iter().position(|mark| mark.name == name).map(|index| self.marks.remove(index))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Mark> {
+        self.marks.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.marks.len()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<Mark> {
+        self.marks.iter()
+    }
+
+    /// Update the marks after the range in the buffer was replaced by `new_len` bytes
+    pub fn splice(&mut self, range: &Range<usize>, new_len: usize) {
+        for mark in self.marks.iter_mut() {
+            mark.pos = shift_offset(mark.pos, range, new_len);
+        }
+    }
+}
+
+/// A named range of the buffer with a comment about it
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub name: String,
+    pub range: Range<usize>,
+    pub comment: String,
+}
+
+/// The annotated ranges of a buffer, kept sorted by their start.
+#[derive(Debug)]
+pub struct Annotations {
+    annotations: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations {
+            annotations: vec![],
+        }
+    }
+
+    pub fn add(&mut self, annotation: Annotation) {
+        let index = match self.annotations.binary_search_by(
+                |other| other.range.start.cmp(&annotation.range.start)) {
+            Ok(index) | Err(index) => index,
+        };
+        self.annotations.insert(index, annotation);
+    }
+
+    /// Get the annotation covering a position. If a few do, the one starting last is returned, as
+    /// it is usually the most specific.
+    pub fn at(&self, pos: usize) -> Option<&Annotation> {
+        self.annotations.iter().rev().find(|annotation|
+            annotation.range.start <= pos && pos < annotation.range.end)
+    }
+
+    /// Get the parts of the annotated ranges that overlap a range, such as the part of the buffer
+    /// on the screen, sorted by their start.
+    pub fn ranges_in(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        // Only the annotations that start before the end of the range can overlap it
+        let end = match self.annotations.binary_search_by(|annotation|
+                if annotation.range.start < range.end { cmp::Ordering::Less } else { cmp::Ordering::Greater }) {
+            Ok(index) | Err(index) => index,
+        };
+        self.annotations[..end].iter().filter(|annotation| annotation.range.end > range.start).map(|annotation|
+            cmp::max(annotation.range.start, range.start)..cmp::min(annotation.range.end, range.end)
+        ).collect()
+    }
+
+    /// Remove the annotation covering a position, see [at](#method.at).
+    pub fn remove_at(&mut self, pos: usize) -> Option<Annotation> {
+        self.annotations.iter().rposition(|annotation|
+            annotation.range.start <= pos && pos < annotation.range.end
+        ).map(|index| self.annotations.remove(index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<Annotation> {
+        self.annotations.iter()
+    }
+
+    /// Update the annotations after the range in the buffer was replaced by `new_len` bytes
+    pub fn splice(&mut self, range: &Range<usize>, new_len: usize) {
+        for annotation in self.annotations.iter_mut() {
+            annotation.range = shift_offset(annotation.range.start, range, new_len)..
+                               shift_offset(annotation.range.end, range, new_len);
+        }
+        self.annotations.retain(|annotation| annotation.range.start < annotation.range.end);
+    }
+}
+
+fn invalid_sidecar<S: Into<String>>(msg: S) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn get_sidecar_field(table: &toml::Table, name: &str) -> io::Result<toml::Value> {
+    table.get(name).cloned().ok_or(invalid_sidecar(format!("Missing field {}", name)))
+}
+
+fn get_sidecar_pos(table: &toml::Table, name: &str) -> io::Result<usize> {
+    match try!(get_sidecar_field(table, name)) {
+        toml::Value::Integer(i) if i >= 0 => Ok(i as usize),
+        _ => Err(invalid_sidecar(format!("Field {} should be a position", name))),
+    }
+}
+
+fn get_sidecar_string(table: &toml::Table, name: &str) -> io::Result<String> {
+    match try!(get_sidecar_field(table, name)) {
+        toml::Value::String(s) => Ok(s),
+        _ => Err(invalid_sidecar(format!("Field {} should be a string", name))),
+    }
+}
+
+/// Get the tables in an array of tables, such as all the `[[mark]]` entries.
+fn get_sidecar_entries(table: &toml::Table, name: &str) -> io::Result<Vec<toml::Table>> {
+    match table.get(name) {
+        None => Ok(vec![]),
+        Some(&toml::Value::Array(ref entries)) => entries.iter().map(|entry| match *entry {
+            toml::Value::Table(ref t) => Ok(t.clone()),
+            _ => Err(invalid_sidecar(format!("{} should be a table", name))),
+        }).collect(),
+        Some(_) => Err(invalid_sidecar(format!("{} should be an array of tables", name))),
+    }
+}
+
+/// Read the marks and annotations from a sidecar file, as written by
+/// [write_sidecar](fn.write_sidecar.html).
+pub fn read_sidecar<R: Read>(r: &mut R) -> io::Result<(Marks, Annotations)> {
+    let mut s = String::new();
+    try!(r.read_to_string(&mut s));
+
+    let mut parser = toml::Parser::new(&s);
+    let table = match parser.parse() {
+        Some(table) => table,
+        None => return Err(invalid_sidecar(format!("{}", parser.errors[0]))),
+    };
+
+    let mut marks = Marks::new();
+    for entry in try!(get_sidecar_entries(&table, "mark")) {
+        marks.set(try!(get_sidecar_string(&entry, "name")), try!(get_sidecar_pos(&entry, "pos")));
+    }
+
+    let mut annotations = Annotations::new();
+    for entry in try!(get_sidecar_entries(&table, "annotation")) {
+        annotations.add(Annotation {
+            name: try!(get_sidecar_string(&entry, "name")),
+            range: try!(get_sidecar_pos(&entry, "start"))..try!(get_sidecar_pos(&entry, "end")),
+            comment: try!(get_sidecar_string(&entry, "comment")),
+        });
+    }
+
+    Ok((marks, annotations))
+}
+
+/// Write the marks and annotations to a sidecar file. The file is TOML, so that it is easy to
+/// read and share.
+pub fn write_sidecar<W: Write>(w: &mut W, marks: &Marks, annotations: &Annotations) -> io::Result<()> {
+    let mut table = toml::Table::new();
+
+    let mark_entries = marks.iter().map(|mark| {
+        let mut entry = toml::Table::new();
+        entry.insert("name".to_string(), toml::Value::String(mark.name.clone()));
+        entry.insert("pos".to_string(), toml::Value::Integer(mark.pos as i64));
+        toml::Value::Table(entry)
+    }).collect();
+    table.insert("mark".to_string(), toml::Value::Array(mark_entries));
+
+    let annotation_entries = annotations.iter().map(|annotation| {
+        let mut entry = toml::Table::new();
+        entry.insert("name".to_string(), toml::Value::String(annotation.name.clone()));
+        entry.insert("start".to_string(), toml::Value::Integer(annotation.range.start as i64));
+        entry.insert("end".to_string(), toml::Value::Integer(annotation.range.end as i64));
+        entry.insert("comment".to_string(), toml::Value::String(annotation.comment.clone()));
+        toml::Value::Table(entry)
+    }).collect();
+    table.insert("annotation".to_string(), toml::Value::Array(annotation_entries));
+
+    write!(w, "{}", toml::Value::Table(table))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shift_offset() {
+        // Insertion
+        assert_eq!(shift_offset(10, &(5..5), 3), 13);
+        assert_eq!(shift_offset(5, &(5..5), 3), 8);
+        assert_eq!(shift_offset(4, &(5..5), 3), 4);
+        // Deletion
+        assert_eq!(shift_offset(10, &(5..8), 0), 7);
+        assert_eq!(shift_offset(6, &(5..8), 0), 5);
+        // Overwrite
+        assert_eq!(shift_offset(6, &(5..8), 3), 6);
+        assert_eq!(shift_offset(8, &(5..8), 3), 8);
+    }
+
+    #[test]
+    fn test_marks_sorted() {
+        let mut marks = Marks::new();
+        marks.set("b".to_string(), 20);
+        marks.set("a".to_string(), 10);
+        assert!(marks.set("b".to_string(), 5));
+
+        let names: Vec<&str> = marks.iter().map(|mark| &mark.name[..]).collect();
+        assert_eq!(names, ["b", "a"]);
+    }
+
+    #[test]
+    fn test_annotation_at() {
+        let mut annotations = Annotations::new();
+        annotations.add(Annotation { name: "outer".to_string(), range: 0..100, comment: String::new() });
+        annotations.add(Annotation { name: "inner".to_string(), range: 10..20, comment: String::new() });
+
+        assert_eq!(annotations.at(15).unwrap().name, "inner");
+        assert_eq!(annotations.at(20).unwrap().name, "outer");
+        assert!(annotations.at(100).is_none());
+
+        assert_eq!(annotations.ranges_in(15..200), vec![15..100, 15..20]);
+        assert_eq!(annotations.ranges_in(20..30), vec![20..30]);
+        assert_eq!(annotations.ranges_in(100..200), vec![]);
+
+        // Deleting the whole range drops the annotation
+        annotations.splice(&(5..25), 0);
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations.at(10).unwrap().range, 0..80);
+    }
+
+    #[test]
+    fn test_sidecar_roundtrip() {
+        let mut marks = Marks::new();
+        marks.set("header".to_string(), 0);
+        let mut annotations = Annotations::new();
+        annotations.add(Annotation {
+            name: "crc".to_string(),
+            range: 16..20,
+            comment: "CRC32 of the \"payload\"".to_string(),
+        });
+
+        let mut data = vec![];
+        write_sidecar(&mut data, &marks, &annotations).unwrap();
+        let (read_marks, read_annotations) = read_sidecar(&mut &data[..]).unwrap();
+
+        assert_eq!(read_marks.get(0).unwrap().name, "header");
+        let annotation = read_annotations.at(17).unwrap();
+        assert_eq!(annotation.name, "crc");
+        assert_eq!(annotation.range, 16..20);
+        assert_eq!(annotation.comment, "CRC32 of the \"payload\"");
+    }
+}
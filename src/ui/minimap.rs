@@ -0,0 +1,201 @@
+//! A narrow map of the whole buffer, shaded by the contents of each block.
+use std::cmp;
+use std::ops::Range;
+
+use util::buffer::Buffer;
+use util::rect::Rect;
+
+use super::common::Canceled;
+use super::super::frontend::{Frontend, Style, KeyPress};
+use super::input::Input;
+use super::picklist::PickListActions;
+use super::stats::ByteStats;
+use super::widget::Widget;
+
+/// Shades for a block, from an empty level to a full one
+static SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Blocks bigger than this are only sampled from their start, so big files stay responsive
+const SAMPLE_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MinimapMode {
+    /// Shade by the entropy of each block
+    Entropy,
+    /// Shade by the part of each block that is zero bytes
+    Zeros,
+}
+
+/// The buffer split into one block per row, with a cached level between 0 and 1 for each block.
+pub struct Minimap {
+    mode: MinimapMode,
+    levels: Vec<f64>,
+    buffer_len: usize,
+    valid: bool,
+}
+
+impl Minimap {
+    pub fn new(mode: MinimapMode) -> Minimap {
+        Minimap {
+            mode: mode,
+            levels: vec![],
+            buffer_len: 0,
+            valid: false,
+        }
+    }
+
+    pub fn mode(&self) -> MinimapMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: MinimapMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.valid = false;
+        }
+    }
+
+    /// Mark the levels for recalculation, after the buffer was edited.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /// The number of rows used for a buffer, there are no more rows than bytes.
+    pub fn rows_for(buffer_len: usize, height: usize) -> usize {
+        cmp::min(buffer_len, height)
+    }
+
+    /// The range of the buffer shown in a row.
+    pub fn row_range(buffer_len: usize, rows: usize, row: usize) -> Range<usize> {
+        (buffer_len * row / rows)..(buffer_len * (row + 1) / rows)
+    }
+
+    /// The row an offset of the buffer is shown in.
+    pub fn row_of(buffer_len: usize, rows: usize, pos: usize) -> usize {
+        if rows == 0 {
+            return 0;
+        }
+        // Rounding can leave the offset in the following row
+        let mut row = cmp::min(pos * rows / buffer_len, rows - 1);
+        while row + 1 < rows && Minimap::row_range(buffer_len, rows, row).end <= pos {
+            row += 1;
+        }
+        row
+    }
+
+    /// Recalculate the levels if the buffer or the height changed since they were calculated.
+    pub fn update<B: Buffer>(&mut self, buffer: &B, height: usize) {
+        let rows = Minimap::rows_for(buffer.len(), height);
+        if self.valid && self.buffer_len == buffer.len() && self.levels.len() == rows {
+            return;
+        }
+
+        let mode = self.mode;
+        self.levels = (0..rows).map(|row| {
+            let range = Minimap::row_range(buffer.len(), rows, row);
+            let range = range.start..cmp::min(range.end, range.start + SAMPLE_LEN);
+            let stats = ByteStats::from_slices(buffer.iter_range_slices(range));
+            match mode {
+                MinimapMode::Entropy => stats.entropy() / 8.0,
+                MinimapMode::Zeros if stats.total() == 0 => 0.0,
+                MinimapMode::Zeros => stats.count(0) as f64 / stats.total() as f64,
+            }
+        }).collect();
+        self.buffer_len = buffer.len();
+        self.valid = true;
+    }
+
+    pub fn levels(&self) -> &[f64] {
+        &self.levels
+    }
+}
+
+/// The character to shade a block with a level between 0 and 1.
+pub fn shade(level: f64) -> char {
+    let index = (level * (SHADES.len() - 1) as f64).round() as usize;
+    SHADES[cmp::min(index, SHADES.len() - 1)]
+}
+
+signal_decl!{MinimapJumpEvent(usize)}
+
+/// Picks a row of the minimap with the arrow keys, signaling the start offset of its block.
+pub struct MinimapJump {
+    buffer_len: usize,
+    rows: usize,
+    row: usize,
+    pub on_selected: MinimapJumpEvent,
+    pub on_cancel: Canceled,
+}
+
+impl MinimapJump {
+    pub fn new(buffer_len: usize, rows: usize, pos: usize) -> MinimapJump {
+        MinimapJump {
+            buffer_len: buffer_len,
+            rows: rows,
+            row: Minimap::row_of(buffer_len, rows, pos),
+            on_selected: Default::default(),
+            on_cancel: Default::default(),
+        }
+    }
+}
+
+impl Widget for MinimapJump {
+    fn input(&mut self, input: &Input, key: KeyPress) -> bool {
+        let action = if let Some(action) = input.picklist_input(key) { action } else {
+            return false;
+        };
+
+        match action {
+            PickListActions::Down => { self.row = cmp::min(self.row + 1, self.rows.saturating_sub(1)); }
+            PickListActions::Up => { self.row = self.row.saturating_sub(1); }
+            PickListActions::Select => {
+                if self.row < self.rows {
+                    self.on_selected.signal(Minimap::row_range(self.buffer_len, self.rows, self.row).start);
+                }
+            }
+            PickListActions::Cancel => { self.on_cancel.signal(None); }
+        };
+        true
+    }
+
+    fn draw(&mut self, rb: &mut Frontend, area: Rect<isize>, _: bool) {
+        rb.set_cursor(-1, -1);
+        if (self.row as isize) < area.height {
+            rb.print_style(area.left as usize, area.top as usize + self.row, Style::MenuShortcut, ">");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::split_vec::SplitVec;
+
+    #[test]
+    fn test_rows() {
+        assert_eq!(Minimap::rows_for(5, 10), 5);
+        assert_eq!(Minimap::row_range(100, 3, 0), 0..33);
+        assert_eq!(Minimap::row_range(100, 3, 2), 66..100);
+        for pos in 0..100 {
+            let range = Minimap::row_range(100, 3, Minimap::row_of(100, 3, pos));
+            assert!(range.start <= pos && pos < range.end);
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut data = vec![0; 256];
+        data.extend((0..256).map(|b| b as u8));
+        let buffer = SplitVec::from_vec(data);
+
+        let mut minimap = Minimap::new(MinimapMode::Entropy);
+        minimap.update(&buffer, 2);
+        assert_eq!(minimap.levels(), [0.0, 1.0]);
+        assert_eq!(shade(minimap.levels()[1]), '█');
+
+        minimap.set_mode(MinimapMode::Zeros);
+        minimap.update(&buffer, 2);
+        assert_eq!(minimap.levels(), [1.0, 1.0 / 256.0]);
+        assert_eq!(shade(minimap.levels()[1]), ' ');
+    }
+}
@@ -5,4 +5,15 @@ mod overlay;
 mod inputline;
 mod menu;
 mod configscreen;
+mod history;
+mod marks;
+mod picklist;
+mod values;
+mod inspector;
+mod transform;
+mod fill;
+mod fixup;
+mod stats;
+mod minimap;
+mod search;
 pub mod view;
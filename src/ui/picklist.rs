@@ -0,0 +1,87 @@
+use std::default::Default;
+use std::cmp;
+
+use util;
+use util::rect::Rect;
+
+use super::common::Canceled;
+use super::input::Input;
+use super::widget::Widget;
+use super::super::frontend::{Frontend, Style, KeyPress};
+
+pub enum PickListActions {
+    Up,
+    Down,
+    Select,
+    Cancel,
+}
+
+signal_decl!{PickSelected(usize)}
+
+/// A list of lines the user can pick one of, signaling the index of the picked line.
+pub struct PickList {
+    pub on_cancel: Canceled,
+    pub on_selected: PickSelected,
+    title: String,
+    items: Vec<String>,
+    cursor_line: usize,
+}
+
+impl PickList {
+    pub fn new(title: String, items: Vec<String>) -> PickList {
+        PickList {
+            on_cancel: Default::default(),
+            on_selected: Default::default(),
+            title: title,
+            items: items,
+            cursor_line: 0,
+        }
+    }
+}
+
+impl Widget for PickList {
+    fn input(&mut self, input: &Input, key: KeyPress) -> bool {
+        let action = if let Some(action) = input.picklist_input(key) { action } else {
+            return false;
+        };
+
+        match action {
+            PickListActions::Down => {
+                self.cursor_line = cmp::min(self.cursor_line + 1, self.items.len().saturating_sub(1));
+            }
+            PickListActions::Up => { self.cursor_line = self.cursor_line.saturating_sub(1); }
+            PickListActions::Select => {
+                if self.cursor_line < self.items.len() {
+                    self.on_selected.signal(self.cursor_line);
+                }
+            }
+            PickListActions::Cancel => { self.on_cancel.signal(None); }
+        };
+        return true;
+    }
+
+    fn draw(&mut self, rb: &mut Frontend, area: Rect<isize>, _: bool) {
+        rb.set_cursor(-1, -1);
+        let clear_line = util::string_with_repeat(' ', area.width as usize);
+
+        for i in 0..(area.height as usize) {
+            rb.print_style(area.left as usize, area.top as usize + i, Style::Default, &clear_line);
+        }
+
+        rb.print_style(area.left as usize, area.top as usize, Style::MenuTitle, &self.title);
+
+        // Scroll the list so the cursor line is always visible
+        let list_height = cmp::max(area.height as usize, 2) - 1;
+        let first_line = (self.cursor_line + 1).saturating_sub(list_height);
+
+        for (i, item) in self.items.iter().enumerate().skip(first_line).take(list_height) {
+            let style = if i != self.cursor_line {
+                Style::Default
+            } else {
+                Style::Selection
+            };
+            let line = &item[0..cmp::min(item.len(), area.width as usize)];
+            rb.print_style(area.left as usize, area.top as usize + 1 + i - first_line, style, line);
+        }
+    }
+}
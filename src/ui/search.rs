@@ -0,0 +1,307 @@
+//! What the buffer can be searched for.
+use std::cmp;
+use std::mem;
+
+use util::buffer::{Buffer, find_any_masked_in_iter};
+use util::regex::Regex;
+
+use super::values::{ValueType, ValueKind, uint_to_bytes};
+
+/// Searching backward for a regex goes over the data in windows of this size, from the end back
+const REGEX_RFIND_WINDOW: usize = 0x10000;
+
+/// How far past the end of a window a match that starts in it is looked for, when the regex has no
+/// longest match
+const REGEX_RFIND_MAX_LEN: usize = 0x10000;
+
+/// A needle to search the buffer for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Needle {
+    Bytes(Vec<u8>),
+    /// Bytes that only need to match in the bits set in the mask
+    Masked(Vec<u8>, Vec<u8>),
+    /// Several masked needles of the same length, any of which can match
+    AnyMasked(Vec<(Vec<u8>, Vec<u8>)>),
+    Regex(Regex),
+}
+
+impl Needle {
+    /// Parse hex bytes, where `?` is a wildcard for a nibble, such as `de ad ?? 4? be ef`.
+    pub fn parse_hex(s: &str) -> Option<Needle> {
+        let nibbles: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if nibbles.len() % 2 != 0 {
+            return None;
+        }
+
+        let mut bytes = vec![];
+        let mut mask = vec![];
+        for pair in nibbles.chunks(2) {
+            let mut b = 0;
+            let mut m = 0;
+            for &c in pair {
+                b <<= 4;
+                m <<= 4;
+                if c != '?' {
+                    match c.to_digit(16) {
+                        Some(digit) => b |= digit as u8,
+                        None => return None,
+                    }
+                    m |= 0xf;
+                }
+            }
+            bytes.push(b);
+            mask.push(m);
+        }
+
+        if mask.iter().all(|&m| m == 0xff) {
+            Some(Needle::Bytes(bytes))
+        } else {
+            Some(Needle::Masked(bytes, mask))
+        }
+    }
+
+    /// Parse a value of the given type, floats can be followed by a tolerance such as `1.5~0.01`
+    /// to find any value in that range.
+    pub fn parse_value(s: &str, value_type: ValueType) -> Result<Needle, String> {
+        let (value, tolerance) = match s.find('~') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let tolerance = match tolerance {
+            None => return value_type.parse(value).map(Needle::Bytes),
+            Some(_) if value_type.kind != ValueKind::Float => {
+                return Err("Tolerances are only for floats".to_string());
+            }
+            Some(tolerance) => tolerance,
+        };
+
+        let parse_float = |s: &str| match s.trim().parse::<f64>() {
+            Ok(val) if val.is_finite() => Ok(val),
+            _ => Err(format!("Invalid {}", value_type)),
+        };
+        let value = try!(parse_float(value));
+        let tolerance = try!(parse_float(tolerance));
+        if tolerance < 0.0 {
+            return Err("Invalid tolerance".to_string());
+        }
+
+        let bits = value_type.size * 8;
+        let size_mask = if bits == 64 { !0 } else { (1 << bits) - 1 };
+        let needles = float_bit_ranges(value - tolerance, value + tolerance, value_type.size).into_iter()
+            .flat_map(|(lo, hi)| range_prefixes(lo, hi, bits))
+            .map(|(prefix, mask)| (uint_to_bytes(prefix, value_type.size, value_type.little_endian),
+                                   uint_to_bytes(mask & size_mask, value_type.size, value_type.little_endian)))
+            .collect();
+        Ok(Needle::AnyMasked(needles))
+    }
+
+    /// Find the first match at from or after it.
+    pub fn find_from<B: Buffer>(&self, buffer: &B, from: usize) -> Option<usize> {
+        match *self {
+            Needle::Bytes(ref bytes) => buffer.find_slice_from(from, bytes),
+            Needle::Masked(ref bytes, ref mask) => buffer.find_masked_from(from, bytes, mask),
+            Needle::AnyMasked(ref needles) if from < buffer.len() => {
+                let needles: Vec<(&[u8], &[u8])> = needles.iter().map(|&(ref bytes, ref mask)|
+                    (&bytes[..], &mask[..])
+                ).collect();
+                find_any_masked_in_iter(buffer.iter_range(from..buffer.len()), &needles).map(|pos| from + pos)
+            }
+            Needle::AnyMasked(_) => None,
+            Needle::Regex(ref regex) if from < buffer.len() =>
+                regex.find_in_slices(buffer.iter_range_slices(from..buffer.len())).map(|pos| from + pos),
+            Needle::Regex(_) => None,
+        }
+    }
+
+    /// Find the last match that starts before from.
+    pub fn rfind_from<B: Buffer>(&self, buffer: &B, from: usize) -> Option<usize> {
+        match *self {
+            Needle::Bytes(ref bytes) => buffer.rfind_slice_from(from, bytes),
+            Needle::Masked(ref bytes, ref mask) => buffer.rfind_masked_from(from, bytes, mask),
+            Needle::AnyMasked(ref needles) => {
+                let needles: Vec<(&[u8], &[u8])> = needles.iter().map(|&(ref bytes, ref mask)|
+                    (&bytes[..], &mask[..])
+                ).collect();
+                buffer.rfind_any_masked_from(from, &needles)
+            }
+            Needle::Regex(ref regex) => {
+                // Each window is searched together with the longest match past its end, so that
+                // all matches that start in it are found
+                let overlap = regex.max_len().unwrap_or(REGEX_RFIND_MAX_LEN);
+                let mut end = cmp::min(from, buffer.len());
+                while end > 0 {
+                    let start = end.saturating_sub(REGEX_RFIND_WINDOW);
+                    let data_end = cmp::min(buffer.len(), end.saturating_add(overlap));
+                    let slices = buffer.iter_range_slices(start..data_end);
+                    if let Some(pos) = regex.rfind_in_slices(slices, end - start) {
+                        return Some(start + pos);
+                    }
+                    end = start;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// The ranges of the bit patterns of the floats of `size` bytes between lo and hi. Floats of the
+/// same sign are ordered like their bit patterns, so there is a range for each sign.
+fn float_bit_ranges(lo: f64, hi: f64, size: usize) -> Vec<(u64, u64)> {
+    let to_bits = |val: f64| -> u64 {
+        if size == 4 {
+            unsafe { mem::transmute::<f32, u32>(val as f32) as u64 }
+        } else {
+            unsafe { mem::transmute::<f64, u64>(val) }
+        }
+    };
+    let sign = 1u64 << (size * 8 - 1);
+
+    let mut ranges = vec![];
+    if lo < 0.0 {
+        // Negative floats have bigger patterns for bigger magnitudes
+        let smallest = if hi < 0.0 { -hi } else { 0.0 };
+        ranges.push((sign | to_bits(smallest), sign | to_bits(-lo)));
+    }
+    if hi >= 0.0 {
+        let smallest = if lo > 0.0 { lo } else { 0.0 };
+        ranges.push((to_bits(smallest), to_bits(hi)));
+    }
+    ranges
+}
+
+/// Split the range of integers lo..=hi of `bits` bits into aligned blocks, each given by its value
+/// and the mask of the bits that are the same in the whole block.
+fn range_prefixes(mut lo: u64, hi: u64, bits: usize) -> Vec<(u64, u64)> {
+    let mut prefixes = vec![];
+    loop {
+        // The biggest aligned block that starts at lo and doesn't go past hi
+        let mut block_bits = cmp::min(lo.trailing_zeros() as usize, bits);
+        let block_mask = |block_bits: usize| if block_bits >= 64 { 0 } else { !((1u64 << block_bits) - 1) };
+        while block_bits > 0 && lo | !block_mask(block_bits) > hi {
+            block_bits -= 1;
+        }
+        let block_mask = block_mask(block_bits);
+        prefixes.push((lo, block_mask));
+
+        let last = lo | !block_mask;
+        if last >= hi {
+            break;
+        }
+        lo = last + 1;
+    }
+    prefixes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use util::split_vec::SplitVec;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(Needle::parse_hex("de AD"), Some(Needle::Bytes(vec![0xde, 0xad])));
+        assert_eq!(Needle::parse_hex("de ?? 4?"),
+            Some(Needle::Masked(vec![0xde, 0x00, 0x40], vec![0xff, 0x00, 0xf0])));
+        assert_eq!(Needle::parse_hex("de a"), None);
+        assert_eq!(Needle::parse_hex("dg"), None);
+    }
+
+    #[test]
+    fn test_rfind() {
+        let buffer = SplitVec::from_vec(b"abcabcab".to_vec());
+        let bytes = Needle::Bytes(b"ab".to_vec());
+        assert_eq!(bytes.rfind_from(&buffer, buffer.len()), Some(6));
+        assert_eq!(bytes.rfind_from(&buffer, 6), Some(3));
+        assert_eq!(bytes.rfind_from(&buffer, 0), None);
+
+        let masked = Needle::parse_hex("6? 63").unwrap();
+        assert_eq!(masked.rfind_from(&buffer, 5), Some(4));
+        assert_eq!(masked.rfind_from(&buffer, 4), Some(1));
+
+        let regex = Needle::Regex(Regex::new("ca+").unwrap());
+        assert_eq!(regex.rfind_from(&buffer, buffer.len()), Some(5));
+        assert_eq!(regex.rfind_from(&buffer, 5), Some(2));
+        assert_eq!(regex.rfind_from(&buffer, 2), None);
+
+        let empty = Needle::Regex(Regex::new("x*").unwrap());
+        assert_eq!(empty.rfind_from(&buffer, buffer.len()), Some(7));
+        assert_eq!(empty.rfind_from(&buffer, 0), None);
+    }
+
+    #[test]
+    fn test_rfind_across_windows() {
+        // Matches that start in one window and end in the next
+        let mut data = vec![0; 3 * REGEX_RFIND_WINDOW];
+        data[REGEX_RFIND_WINDOW - 2..REGEX_RFIND_WINDOW + 3].copy_from_slice(b"abbbc");
+        data[2 * REGEX_RFIND_WINDOW - 2..2 * REGEX_RFIND_WINDOW + 2].copy_from_slice(b"\x7fELF");
+        let buffer = SplitVec::from_vec(data);
+
+        let unbounded = Needle::Regex(Regex::new("ab+c").unwrap());
+        assert_eq!(unbounded.rfind_from(&buffer, buffer.len()), Some(REGEX_RFIND_WINDOW - 2));
+        let bounded = Needle::Regex(Regex::new("\\x7fELF").unwrap());
+        assert_eq!(bounded.rfind_from(&buffer, buffer.len()), Some(2 * REGEX_RFIND_WINDOW - 2));
+        assert_eq!(bounded.rfind_from(&buffer, 2 * REGEX_RFIND_WINDOW - 2), None);
+        let bytes = Needle::Bytes(b"bbbc".to_vec());
+        assert_eq!(bytes.rfind_from(&buffer, buffer.len()), Some(REGEX_RFIND_WINDOW - 1));
+    }
+
+    #[test]
+    fn test_find_value_range() {
+        let mut data = vec![0; 0x100];
+        // 1.6 as an f32 BE, outside of the range
+        data[0x10..0x14].copy_from_slice(&[0x3f, 0xcc, 0xcc, 0xcd]);
+        // 1.5001 as an f32 BE and LE
+        data[0x20..0x24].copy_from_slice(&[0x3f, 0xc0, 0x06, 0x8e]);
+        data[0x60..0x64].copy_from_slice(&[0x8e, 0x06, 0xc0, 0x3f]);
+        let buffer = SplitVec::from_vec(data);
+
+        let f32_be = ValueType::new(ValueKind::Float, 4, false);
+        let needle = Needle::parse_value("1.5~0.001", f32_be).unwrap();
+        assert_eq!(needle.find_from(&buffer, 0), Some(0x20));
+        assert_eq!(needle.find_from(&buffer, 0x21), None);
+        assert_eq!(needle.rfind_from(&buffer, buffer.len()), Some(0x20));
+
+        let f32_le = ValueType::new(ValueKind::Float, 4, true);
+        let needle = Needle::parse_value("1.5~0.001", f32_le).unwrap();
+        assert_eq!(needle.find_from(&buffer, 0), Some(0x60));
+        assert_eq!(needle.rfind_from(&buffer, 0x60), None);
+    }
+
+    #[test]
+    fn test_range_prefixes() {
+        assert_eq!(range_prefixes(4, 7, 8), vec![(4, !3)]);
+        assert_eq!(range_prefixes(3, 8, 8), vec![(3, !0), (4, !3), (8, !0)]);
+        assert_eq!(range_prefixes(0, !0, 64), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_parse_value() {
+        let u16_le = ValueType::new(ValueKind::Unsigned, 2, true);
+        assert_eq!(Needle::parse_value("0x1234", u16_le), Ok(Needle::Bytes(vec![0x34, 0x12])));
+        assert!(Needle::parse_value("1~1", u16_le).is_err());
+
+        // 1.5 is 0x3fc00000, the range covers patterns that differ in the low bits
+        let f32_be = ValueType::new(ValueKind::Float, 4, false);
+        let needle = Needle::parse_value("1.5~0.001", f32_be).unwrap();
+        let matches = |data: &[u8]| match needle {
+            Needle::AnyMasked(ref needles) => needles.iter().any(|&(ref bytes, ref mask)|
+                data.iter().zip(mask).map(|(b, m)| b & m).eq(bytes.iter().cloned())
+            ),
+            _ => false,
+        };
+        assert!(matches(&[0x3f, 0xc0, 0x00, 0x00]));
+        assert!(matches(&[0x3f, 0xc0, 0x10, 0x00]));
+        assert!(matches(&[0x3f, 0xbf, 0xf0, 0x00]));
+        assert!(!matches(&[0x3f, 0xc1, 0x00, 0x00]));
+        assert!(!matches(&[0xbf, 0xc0, 0x00, 0x00]));
+
+        // Around zero both signs are found
+        let needle = Needle::parse_value("0~1", f32_be).unwrap();
+        if let Needle::AnyMasked(ref needles) = needle {
+            assert!(needles.iter().any(|&(ref bytes, _)| bytes[0] & 0x80 != 0));
+            assert!(needles.iter().any(|&(ref bytes, _)| bytes[0] & 0x80 == 0));
+        } else {
+            panic!("Expected masked needles");
+        }
+    }
+}
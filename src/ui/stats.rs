@@ -0,0 +1,215 @@
+//! Byte frequency statistics of a range, and an overlay that shows them.
+use std::cmp;
+use std::ops::Range;
+
+use util;
+use util::rect::Rect;
+
+use super::common::Canceled;
+use super::super::frontend::{Frontend, Style, KeyPress};
+use super::input::Input;
+use super::overlay::OverlayActions;
+use super::widget::Widget;
+
+/// Block characters for the fractions of a histogram cell, from empty to full
+static BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How often each byte value appears in some data.
+pub struct ByteStats {
+    counts: [u64; 256],
+    total: u64,
+}
+
+impl ByteStats {
+    pub fn new() -> ByteStats {
+        ByteStats {
+            counts: [0; 256],
+            total: 0,
+        }
+    }
+
+    pub fn from_slices<'a, I: Iterator<Item=&'a [u8]>>(slices: I) -> ByteStats {
+        let mut stats = ByteStats::new();
+        for slice in slices {
+            stats.update(slice);
+        }
+        stats
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.counts[b as usize] += 1;
+        }
+        self.total += data.len() as u64;
+    }
+
+    pub fn count(&self, b: u8) -> u64 {
+        self.counts[b as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The Shannon entropy in bits per byte, between 0 for constant data and 8 for random data.
+    pub fn entropy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let total = self.total as f64;
+        self.counts.iter().filter(|&&count| count > 0).map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        }).sum()
+    }
+
+    pub fn min(&self) -> Option<u8> {
+        self.counts.iter().position(|&count| count > 0).map(|b| b as u8)
+    }
+
+    pub fn max(&self) -> Option<u8> {
+        self.counts.iter().rposition(|&count| count > 0).map(|b| b as u8)
+    }
+
+    /// The most common byte, the lowest one on ties.
+    pub fn most_common(&self) -> Option<u8> {
+        if self.total == 0 {
+            return None;
+        }
+        let mut best = 0;
+        for b in 1..256 {
+            if self.counts[b] > self.counts[best] {
+                best = b;
+            }
+        }
+        Some(best as u8)
+    }
+}
+
+/// An overlay with the byte statistics of a range of the buffer.
+pub struct StatsOverlay {
+    stats: ByteStats,
+    range: Range<usize>,
+    pub on_cancel: Canceled,
+}
+
+impl StatsOverlay {
+    pub fn new(stats: ByteStats, range: Range<usize>) -> StatsOverlay {
+        StatsOverlay {
+            stats: stats,
+            range: range,
+            on_cancel: Default::default(),
+        }
+    }
+
+    fn summary(&self) -> Vec<String> {
+        let byte_line = |name: &str, b: Option<u8>| match b {
+            Some(b) => format!("{:<12} {:02x} ({} times)", name, b, self.stats.count(b)),
+            None => format!("{:<12} -", name),
+        };
+        vec![
+            format!("Statistics of {}..{} ({} bytes)", self.range.start, self.range.end, self.stats.total()),
+            format!("{:<12} {:.3} bits per byte", "Entropy:", self.stats.entropy()),
+            byte_line("Min byte:", self.stats.min()),
+            byte_line("Max byte:", self.stats.max()),
+            byte_line("Most common:", self.stats.most_common()),
+        ]
+    }
+
+    /// Draw the histogram as one column per group of byte values, as many as fit the width.
+    fn draw_histogram(&self, rb: &mut Frontend, area: Rect<isize>) {
+        if area.height < 2 || area.width < 1 {
+            return;
+        }
+
+        let mut columns = 256;
+        while columns > area.width as usize && columns > 1 {
+            columns /= 2;
+        }
+        let per_column = 256 / columns;
+        let column_counts: Vec<u64> = (0..columns).map(|i|
+            (i * per_column..(i + 1) * per_column).map(|b| self.stats.count(b as u8)).sum()
+        ).collect();
+        let highest = cmp::max(column_counts.iter().cloned().max().unwrap_or(0), 1);
+
+        // Leave the last line for the axis labels
+        let bar_rows = (area.height - 1) as u64;
+        let bottom = (area.top + area.height - 2) as usize;
+        for (i, &count) in column_counts.iter().enumerate() {
+            // The height of the bar in eighths of a line, rounded up so that rare bytes still show
+            let eighths = (count * bar_rows * 8 + highest - 1) / highest;
+            for row in 0..bar_rows {
+                let fill = cmp::min(eighths.saturating_sub(row * 8), 8) as usize;
+                rb.print_char_style(area.left as usize + i, bottom - row as usize, Style::Default, BLOCKS[fill]);
+            }
+        }
+
+        let axis_y = (area.top + area.height - 1) as usize;
+        rb.print_style(area.left as usize, axis_y, Style::Hint, "00");
+        if columns >= 4 {
+            rb.print_style(area.left as usize + columns - 2, axis_y, Style::Hint, "ff");
+        }
+    }
+}
+
+impl Widget for StatsOverlay {
+    fn input(&mut self, input: &Input, key: KeyPress) -> bool {
+        let action = if let Some(action) = input.overlay_input(key) { action } else {
+            return false;
+        };
+        match action {
+            OverlayActions::Cancel => {
+                self.on_cancel.signal(None);
+                true
+            }
+        }
+    }
+
+    fn draw(&mut self, rb: &mut Frontend, area: Rect<isize>, _: bool) {
+        rb.set_cursor(-1, -1);
+        let clear_line = util::string_with_repeat(' ', area.width as usize);
+        for i in 0..(area.height as usize) {
+            rb.print_style(area.left as usize, area.top as usize + i, Style::Default, &clear_line);
+        }
+
+        let summary = self.summary();
+        for (i, line) in summary.iter().enumerate().take(area.height as usize) {
+            let style = if i == 0 { Style::MenuTitle } else { Style::Default };
+            let line = &line[0..cmp::min(line.len(), area.width as usize)];
+            rb.print_style(area.left as usize, area.top as usize + i, style, line);
+        }
+
+        // Keep an empty line between the summary and the histogram
+        let histogram_top = summary.len() as isize + 1;
+        if area.height > histogram_top {
+            self.draw_histogram(rb, Rect {
+                top: area.top + histogram_top,
+                left: area.left,
+                height: area.height - histogram_top,
+                width: area.width,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_byte_stats() {
+        let stats = ByteStats::from_slices(vec![&b"aab"[..], &b"c"[..]].into_iter());
+        assert_eq!(stats.total(), 4);
+        assert_eq!(stats.min(), Some(b'a'));
+        assert_eq!(stats.max(), Some(b'c'));
+        assert_eq!(stats.most_common(), Some(b'a'));
+        assert!((stats.entropy() - 1.5).abs() < 1e-9);
+
+        let all: Vec<u8> = (0..256).map(|b| b as u8).collect();
+        assert!((ByteStats::from_slices(Some(&all[..]).into_iter()).entropy() - 8.0).abs() < 1e-9);
+
+        let empty = ByteStats::new();
+        assert_eq!(empty.entropy(), 0.0);
+        assert_eq!(empty.most_common(), None);
+    }
+}
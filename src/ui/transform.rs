@@ -0,0 +1,211 @@
+//! Bitwise and arithmetic transforms of the bytes in a selection.
+use rustc_serialize::hex::FromHex;
+
+use super::values::{ValueType, read_uint, uint_to_bytes, step_uint};
+
+/// The kinds of transforms, which are picked before their argument is known.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TransformKind {
+    Xor,
+    And,
+    Or,
+    Not,
+    ShiftLeft,
+    ShiftRight,
+    RotateLeft,
+    RotateRight,
+    Add,
+    Subtract,
+}
+
+impl TransformKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TransformKind::Xor => "XOR",
+            TransformKind::And => "AND",
+            TransformKind::Or => "OR",
+            TransformKind::Not => "NOT",
+            TransformKind::ShiftLeft => "Shift left",
+            TransformKind::ShiftRight => "Shift right",
+            TransformKind::RotateLeft => "Rotate left",
+            TransformKind::RotateRight => "Rotate right",
+            TransformKind::Add => "Add",
+            TransformKind::Subtract => "Subtract",
+        }
+    }
+
+    /// Whether the transform works on words, rather than on each byte
+    pub fn uses_words(&self) -> bool {
+        *self == TransformKind::Add || *self == TransformKind::Subtract
+    }
+
+    /// Parse the argument of the transform: a hex key for the bitwise operations, a bit count for
+    /// shifts and rotates, and a number for arithmetic, which is done on words of `word_type`.
+    pub fn parse(&self, s: &str, word_type: ValueType) -> Result<Transform, String> {
+        let s = s.trim();
+        let parse_key = || -> Result<Vec<u8>, String> {
+            let key: String = s.split_whitespace().collect();
+            match key.from_hex() {
+                Ok(ref key) if key.is_empty() => Err("Empty key".to_string()),
+                Ok(key) => Ok(key),
+                Err(_) => Err("Invalid hex key".to_string()),
+            }
+        };
+        let parse_bits = || -> Result<u32, String> {
+            match s.parse::<u32>() {
+                Ok(bits) if bits < 8 => Ok(bits),
+                _ => Err("Bit count should be between 0 and 7".to_string()),
+            }
+        };
+        let parse_number = || -> Result<u64, String> {
+            word_type.parse(s).map(|bytes| read_uint(&bytes, word_type.size, word_type.little_endian).unwrap())
+        };
+
+        Ok(match *self {
+            TransformKind::Xor => Transform::Xor(try!(parse_key())),
+            TransformKind::And => Transform::And(try!(parse_key())),
+            TransformKind::Or => Transform::Or(try!(parse_key())),
+            TransformKind::Not => Transform::Not,
+            TransformKind::ShiftLeft => Transform::ShiftLeft(try!(parse_bits())),
+            TransformKind::ShiftRight => Transform::ShiftRight(try!(parse_bits())),
+            TransformKind::RotateLeft => Transform::RotateLeft(try!(parse_bits())),
+            TransformKind::RotateRight => Transform::RotateRight(try!(parse_bits())),
+            TransformKind::Add => Transform::Add(try!(parse_number()), word_type),
+            TransformKind::Subtract => Transform::Subtract(try!(parse_number()), word_type),
+        })
+    }
+}
+
+/// A transform with its argument, ready to be applied.
+#[derive(Debug, Clone)]
+pub enum Transform {
+    /// XOR with a key that repeats across the data
+    Xor(Vec<u8>),
+    And(Vec<u8>),
+    Or(Vec<u8>),
+    Not,
+    ShiftLeft(u32),
+    ShiftRight(u32),
+    RotateLeft(u32),
+    RotateRight(u32),
+    /// Add to each word, wrapping on overflow
+    Add(u64, ValueType),
+    Subtract(u64, ValueType),
+    /// Reverse the order of the bytes in each word of the given size
+    ByteSwap(usize),
+}
+
+impl Transform {
+    pub fn name(&self) -> &'static str {
+        let kind = match *self {
+            Transform::Xor(_) => TransformKind::Xor,
+            Transform::And(_) => TransformKind::And,
+            Transform::Or(_) => TransformKind::Or,
+            Transform::Not => TransformKind::Not,
+            Transform::ShiftLeft(_) => TransformKind::ShiftLeft,
+            Transform::ShiftRight(_) => TransformKind::ShiftRight,
+            Transform::RotateLeft(_) => TransformKind::RotateLeft,
+            Transform::RotateRight(_) => TransformKind::RotateRight,
+            Transform::Add(_, _) => TransformKind::Add,
+            Transform::Subtract(_, _) => TransformKind::Subtract,
+            Transform::ByteSwap(_) => return "Byte swap",
+        };
+        kind.name()
+    }
+
+    fn map_bytes<F: Fn(usize, u8) -> u8>(data: &mut [u8], f: F) {
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = f(i, *b);
+        }
+    }
+
+    fn check_words(data: &[u8], size: usize) -> Result<(), String> {
+        if data.len() % size != 0 {
+            Err(format!("Selection size {} isn't a multiple of the {} byte word size", data.len(), size))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn step_words(data: &mut [u8], val: u64, word_type: ValueType, add: bool) -> Result<(), String> {
+        let size = word_type.size;
+        try!(Transform::check_words(data, size));
+        for word in data.chunks_mut(size) {
+            let old_val = read_uint(word, size, word_type.little_endian).unwrap();
+            let new_val = step_uint(old_val, val, size, add, false);
+            word.copy_from_slice(&uint_to_bytes(new_val, size, word_type.little_endian));
+        }
+        Ok(())
+    }
+
+    /// Transform the data in place. Fails without changing it if the data doesn't fit the
+    /// transform, such as a size that isn't a multiple of the word size.
+    pub fn apply(&self, data: &mut [u8]) -> Result<(), String> {
+        match *self {
+            Transform::Xor(ref key) => Transform::map_bytes(data, |i, b| b ^ key[i % key.len()]),
+            Transform::And(ref key) => Transform::map_bytes(data, |i, b| b & key[i % key.len()]),
+            Transform::Or(ref key) => Transform::map_bytes(data, |i, b| b | key[i % key.len()]),
+            Transform::Not => Transform::map_bytes(data, |_, b| !b),
+            Transform::ShiftLeft(bits) => Transform::map_bytes(data, |_, b| b << bits),
+            Transform::ShiftRight(bits) => Transform::map_bytes(data, |_, b| b >> bits),
+            Transform::RotateLeft(bits) => Transform::map_bytes(data, |_, b| b.rotate_left(bits)),
+            Transform::RotateRight(bits) => Transform::map_bytes(data, |_, b| b.rotate_right(bits)),
+            Transform::Add(val, word_type) => return Transform::step_words(data, val, word_type, true),
+            Transform::Subtract(val, word_type) => return Transform::step_words(data, val, word_type, false),
+            Transform::ByteSwap(size) => {
+                try!(Transform::check_words(data, size));
+                for word in data.chunks_mut(size) {
+                    word.reverse();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::values::{ValueType, ValueKind};
+
+    fn transformed(kind: TransformKind, arg: &str, word_type: ValueType, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut data = data.to_vec();
+        try!(try!(kind.parse(arg, word_type)).apply(&mut data));
+        Ok(data)
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let byte = ValueType::new(ValueKind::Unsigned, 1, false);
+        assert_eq!(transformed(TransformKind::Xor, "ff 01", byte, &[0x0f, 0x0f, 0xf0]).unwrap(),
+            [0xf0, 0x0e, 0x0f]);
+        assert_eq!(transformed(TransformKind::Not, "", byte, &[0x0f]).unwrap(), [0xf0]);
+        assert_eq!(transformed(TransformKind::RotateLeft, "4", byte, &[0x12]).unwrap(), [0x21]);
+        assert_eq!(transformed(TransformKind::ShiftRight, "1", byte, &[0x81]).unwrap(), [0x40]);
+        assert!(transformed(TransformKind::And, "f", byte, &[0x0f]).is_err());
+        assert!(transformed(TransformKind::ShiftLeft, "8", byte, &[0x0f]).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let word = ValueType::new(ValueKind::Unsigned, 2, true);
+        assert_eq!(transformed(TransformKind::Add, "1", word, &[0xff, 0x00, 0xff, 0xff]).unwrap(),
+            [0x00, 0x01, 0x00, 0x00]);
+        assert_eq!(transformed(TransformKind::Subtract, "0x10", word, &[0x00, 0x00]).unwrap(),
+            [0xf0, 0xff]);
+        assert!(transformed(TransformKind::Add, "1", word, &[0x00, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_byte_swap() {
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        Transform::ByteSwap(4).apply(&mut data).unwrap();
+        assert_eq!(data, [4, 3, 2, 1, 8, 7, 6, 5]);
+        Transform::ByteSwap(8).apply(&mut data).unwrap();
+        assert_eq!(data, [5, 6, 7, 8, 1, 2, 3, 4]);
+
+        let mut data = vec![1, 2, 3];
+        assert!(Transform::ByteSwap(2).apply(&mut data).is_err());
+        assert_eq!(data, [1, 2, 3]);
+    }
+}
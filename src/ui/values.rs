@@ -0,0 +1,202 @@
+//! Conversion between typed values, such as integers and floats, and their bytes.
+use std::cmp;
+use std::fmt;
+use std::mem;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValueKind {
+    Unsigned,
+    Signed,
+    Float,
+}
+
+/// The type of a value in the buffer: its kind, its size in bytes and its byte order.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ValueType {
+    pub kind: ValueKind,
+    pub size: usize,
+    pub little_endian: bool,
+}
+
+impl ValueType {
+    pub fn new(kind: ValueKind, size: usize, little_endian: bool) -> ValueType {
+        ValueType {
+            kind: kind,
+            size: size,
+            little_endian: little_endian,
+        }
+    }
+
+    /// Change the size, switching floats to integers when there is no float of that size.
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+        if self.kind == ValueKind::Float && size < 4 {
+            self.kind = ValueKind::Unsigned;
+        }
+    }
+
+    /// Change the kind, growing the size to a float's when switching to one.
+    pub fn set_kind(&mut self, kind: ValueKind) {
+        self.kind = kind;
+        if kind == ValueKind::Float && self.size < 4 {
+            self.size = 4;
+        }
+    }
+
+    /// Parse a value of this type, returning its bytes. Integers can also be given in hex with a
+    /// `0x` prefix.
+    pub fn parse(&self, s: &str) -> Result<Vec<u8>, String> {
+        let s = s.trim();
+        let bits = self.size * 8;
+        let val = match self.kind {
+            ValueKind::Unsigned => {
+                let val = try!(parse_int(s).and_then(|(neg, val)| if neg { None } else { Some(val) })
+                    .ok_or(format!("Invalid {}", self)));
+                if bits < 64 && val >> bits != 0 {
+                    return Err(format!("Value too big for {}", self));
+                }
+                val
+            }
+            ValueKind::Signed => {
+                let (neg, abs) = try!(parse_int(s).ok_or(format!("Invalid {}", self)));
+                let limit = 1u64 << (bits - 1);
+                if (neg && abs > limit) || (!neg && abs >= limit) {
+                    return Err(format!("Value out of range for {}", self));
+                }
+                if neg { abs.wrapping_neg() } else { abs }
+            }
+            ValueKind::Float if self.size == 4 => {
+                let val = try!(s.parse::<f32>().map_err(|_| format!("Invalid {}", self)));
+                unsafe { mem::transmute::<f32, u32>(val) as u64 }
+            }
+            ValueKind::Float => {
+                let val = try!(s.parse::<f64>().map_err(|_| format!("Invalid {}", self)));
+                unsafe { mem::transmute::<f64, u64>(val) }
+            }
+        };
+        Ok(uint_to_bytes(val, self.size, self.little_endian))
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            ValueKind::Unsigned => "u",
+            ValueKind::Signed => "i",
+            ValueKind::Float => "f",
+        };
+        let endian = if self.little_endian { "LE" } else { "BE" };
+        if self.size == 1 {
+            write!(f, "{}8", kind)
+        } else {
+            write!(f, "{}{} {}", kind, self.size * 8, endian)
+        }
+    }
+}
+
+/// Parse an integer, in decimal or in hex with a `0x` prefix, returning if it is negative and its
+/// absolute value.
+pub fn parse_int(s: &str) -> Option<(bool, u64)> {
+    let (neg, s) = if s.starts_with('-') { (true, &s[1..]) } else { (false, s) };
+    let val = if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16)
+    } else {
+        u64::from_str_radix(s, 10)
+    };
+    val.ok().map(|val| (neg, val))
+}
+
+/// Read an unsigned integer of `size` bytes from the start of data.
+pub fn read_uint(data: &[u8], size: usize, little_endian: bool) -> Option<u64> {
+    if data.len() < size {
+        return None;
+    }
+
+    let bytes = &data[..size];
+    let fold = |val: u64, b: &u8| (val << 8) | *b as u64;
+    Some(if little_endian {
+        bytes.iter().rev().fold(0, fold)
+    } else {
+        bytes.iter().fold(0, fold)
+    })
+}
+
+/// Sign extend an integer of `size` bytes.
+pub fn to_signed(val: u64, size: usize) -> i64 {
+    let shift = 64 - size * 8;
+    ((val << shift) as i64) >> shift
+}
+
+/// The bytes of the lowest `size` bytes of an integer.
+pub fn uint_to_bytes(val: u64, size: usize, little_endian: bool) -> Vec<u8> {
+    let mut bytes: Vec<u8> = (0..size).map(|i| (val >> (i * 8)) as u8).collect();
+    if !little_endian {
+        bytes.reverse();
+    }
+    bytes
+}
+
+/// Add or subtract a step from an unsigned integer of `size` bytes. On overflow the result either
+/// wraps around or saturates at the smallest or biggest value.
+pub fn step_uint(val: u64, step: u64, size: usize, increment: bool, saturate: bool) -> u64 {
+    let max = if size >= 8 { !0 } else { (1 << (size * 8)) - 1 };
+    match (increment, saturate) {
+        (true, false) => val.wrapping_add(step) & max,
+        (false, false) => val.wrapping_sub(step) & max,
+        (true, true) => val.checked_add(step).map_or(max, |new_val| cmp::min(new_val, max)),
+        (false, true) => val.saturating_sub(step),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_uint() {
+        let data = [0x01, 0x02, 0x03, 0x04];
+        assert_eq!(read_uint(&data, 2, true), Some(0x0201));
+        assert_eq!(read_uint(&data, 4, false), Some(0x01020304));
+        assert_eq!(read_uint(&data, 8, false), None);
+    }
+
+    #[test]
+    fn test_parse_ints() {
+        let u32_le = ValueType::new(ValueKind::Unsigned, 4, true);
+        assert_eq!(u32_le.parse("0x01020304").unwrap(), [4, 3, 2, 1]);
+        assert_eq!(u32_le.parse("4294967295").unwrap(), [0xff; 4]);
+        assert!(u32_le.parse("4294967296").is_err());
+        assert!(u32_le.parse("-1").is_err());
+
+        let i16_be = ValueType::new(ValueKind::Signed, 2, false);
+        assert_eq!(i16_be.parse("-2").unwrap(), [0xff, 0xfe]);
+        assert_eq!(i16_be.parse("-32768").unwrap(), [0x80, 0x00]);
+        assert!(i16_be.parse("32768").is_err());
+    }
+
+    #[test]
+    fn test_parse_floats() {
+        let f32_be = ValueType::new(ValueKind::Float, 4, false);
+        assert_eq!(f32_be.parse("1.5").unwrap(), [0x3f, 0xc0, 0, 0]);
+        assert!(f32_be.parse("abc").is_err());
+    }
+
+    #[test]
+    fn test_step_uint() {
+        assert_eq!(step_uint(0xff, 1, 1, true, false), 0);
+        assert_eq!(step_uint(0xff, 1, 1, true, true), 0xff);
+        assert_eq!(step_uint(1, 2, 2, false, false), 0xffff);
+        assert_eq!(step_uint(1, 2, 2, false, true), 0);
+        assert_eq!(step_uint(!0, 5, 8, true, true), !0);
+        assert_eq!(step_uint(10, 5, 4, true, false), 15);
+    }
+
+    #[test]
+    fn test_type_changes() {
+        let mut value_type = ValueType::new(ValueKind::Unsigned, 1, true);
+        value_type.set_kind(ValueKind::Float);
+        assert_eq!(format!("{}", value_type), "f32 LE");
+        value_type.set_size(2);
+        assert_eq!(format!("{}", value_type), "u16 LE");
+    }
+}
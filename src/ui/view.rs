@@ -1,6 +1,5 @@
 use std::cmp;
 use std::io;
-use std::io::Read;
 use std::io::Write;
 use std::ops::Range;
 use std::path::Path;
@@ -8,15 +7,19 @@ use std::path::PathBuf;
 use std::iter;
 use std::ascii::AsciiExt;
 use itertools::Itertools;
+use rustc_serialize::hex::ToHex;
 use std::borrow::Cow;
 use std::rc::Rc;
 use std::marker::PhantomData;
 
 use util;
+use util::buffer::{Buffer, SavedData};
 use util::split_vec::SplitVec;
 use util::rect::Rect;
 use util::relative_rect::{RelativeRect, RelativePos, RelativeSize};
 use util::signals::SignalReceiver;
+use util::checksum::{Algorithm, Checksum};
+use util::strings::{find_strings, StringEncoding};
 use super::super::config::{Config, Value, ConfigError};
 
 use super::super::frontend::{Frontend, Style, KeyPress};
@@ -30,10 +33,26 @@ use super::inputline::{
     PathInputLine,
     PathInputType,
     ConfigSetLine,
+    TextInputLine,
+    WriteValueInputLine,
+    TransformInputLine,
+    FillInputLine,
+    ChecksumFixupInputLine,
 };
 use super::overlay::OverlayText;
 use super::configscreen::ConfigScreen;
 use super::menu::{OverlayMenu, MenuState, MenuEntry};
+use super::history::{EditOperation, UndoGroup, History, HistoryKey};
+use super::marks::{Marks, Annotation, Annotations, read_sidecar, write_sidecar};
+use super::picklist::PickList;
+use super::inspector::{inspect, INSPECTOR_DATA_LEN};
+use super::values::{ValueType, ValueKind, read_uint, uint_to_bytes, step_uint};
+use super::transform::{Transform, TransformKind};
+use super::fill::{Fill, MAX_FILL_LEN};
+use super::fixup::ChecksumFixup;
+use super::search::Needle;
+use super::stats::{ByteStats, StatsOverlay};
+use super::minimap::{Minimap, MinimapMode, MinimapJump, shade};
 
 
 custom_derive! {
@@ -60,44 +79,6 @@ impl Nibble {
     }
 }
 
-/// Represents an edit operation done in a buffer, such as paste, insertion and deletion.
-/// Undo operations are also saved as EditOperations that revert the original operation.
-#[derive(Debug, Clone)]
-struct EditOperation {
-    /// The range over which the operation happens
-    range: Range<usize>,
-    /// The data that replaced the supplied range
-    data: Vec<u8>,
-    /// A short description of the type of operation for logging
-    description: &'static str,
-}
-
-impl EditOperation {
-    fn delete(range: Range<usize>) -> EditOperation {
-        EditOperation {
-            range: range,
-            data : vec![],
-            description: "Delete",
-        }
-    }
-
-    fn insert(offset: usize, data: Vec<u8>) -> EditOperation {
-        EditOperation {
-            range: offset..offset,
-            data: data,
-            description: "Insert",
-        }
-    }
-
-    fn write(offset: usize, data: Vec<u8>) -> EditOperation {
-        EditOperation {
-            range: offset..(offset + data.len()),
-            data: data,
-            description: "Overwrite",
-        }
-    }
-}
-
 #[derive(Debug)]
 enum LineNumberMode {
     None,
@@ -119,6 +100,27 @@ static INPUTLINE_LAYOUT : RelativeRect<isize> = RelativeRect {
     height: RelativeSize::Absolute(1),
 };
 
+const INSPECTOR_WIDTH: isize = 36;
+
+static INSPECTOR_LAYOUT : RelativeRect<isize> = RelativeRect {
+    top: RelativePos::FromStart(0),
+    left: RelativePos::FromEnd(INSPECTOR_WIDTH),
+    width: RelativeSize::Absolute(INSPECTOR_WIDTH),
+    height: RelativeSize::Relative(0),
+};
+
+/// The most strings listed in the strings panel, so it stays responsive on big files
+const STRINGS_MAX_COUNT: usize = 100000;
+
+const MINIMAP_WIDTH: isize = 3;
+
+static MINIMAP_LAYOUT : RelativeRect<isize> = RelativeRect {
+    top: RelativePos::FromStart(0),
+    left: RelativePos::FromEnd(MINIMAP_WIDTH),
+    width: RelativeSize::Absolute(MINIMAP_WIDTH),
+    height: RelativeSize::Relative(0),
+};
+
 #[derive(Copy,Clone,Debug)]
 pub enum HexEditActions {
     Edit(char),
@@ -137,53 +139,124 @@ pub enum HexEditActions {
     CutSelection,
     PasteSelection,
     Undo,
+    Redo,
     ToggleInsert,
     ToggleSelecion,
+    StartCount,
+    Increment,
+    Decrement,
+    ToggleInspector,
+    ToggleMinimap,
+    SetMinimapMode(MinimapMode),
+    AskMinimapJump,
     HelpView,
     LogView,
     AskGoto,
     AskFind,
+    FindNext,
+    FindPrevious,
+    AskWriteValue,
+    AskTransform(TransformKind),
+    ByteSwap(usize),
+    AskFill,
+    ShowChecksums,
+    ShowStatistics,
+    ShowStrings,
+    AskChecksumFixup,
+    ApplyChecksumFixup,
     AskOpen,
     AskSave,
     AskConfig,
     AskMarkAdd,
     AskMarkGoto,
+    AskAnnotate,
+    RemoveAnnotation,
     StartMenu,
 }
 
 static ROOT_ENTRIES: MenuState<HexEditActions> = &[
     MenuEntry::CommandEntry('c', "Config", HexEditActions::AskConfig),
+    MenuEntry::CommandEntry('w', "Write value", HexEditActions::AskWriteValue),
+    MenuEntry::CommandEntry('f', "Fill", HexEditActions::AskFill),
+    MenuEntry::CommandEntry('h', "Checksums", HexEditActions::ShowChecksums),
+    MenuEntry::CommandEntry('s', "Statistics", HexEditActions::ShowStatistics),
+    MenuEntry::CommandEntry('n', "Strings", HexEditActions::ShowStrings),
+    MenuEntry::SubEntries('x', "Checksum fix-up", &[
+        MenuEntry::CommandEntry('d', "Define for selection", HexEditActions::AskChecksumFixup),
+        MenuEntry::CommandEntry('a', "Apply again", HexEditActions::ApplyChecksumFixup),
+    ]),
+    MenuEntry::SubEntries('p', "Minimap", &[
+        MenuEntry::CommandEntry('e', "Shade by entropy", HexEditActions::SetMinimapMode(MinimapMode::Entropy)),
+        MenuEntry::CommandEntry('z', "Shade by zero bytes", HexEditActions::SetMinimapMode(MinimapMode::Zeros)),
+        MenuEntry::CommandEntry('j', "Jump", HexEditActions::AskMinimapJump),
+    ]),
     MenuEntry::SubEntries('m', "Mark", &[
         MenuEntry::CommandEntry('a', "Add", HexEditActions::AskMarkAdd),
         MenuEntry::CommandEntry('g', "Goto", HexEditActions::AskMarkGoto),
+        MenuEntry::CommandEntry('n', "Annotate selection", HexEditActions::AskAnnotate),
+        MenuEntry::CommandEntry('r', "Remove annotation", HexEditActions::RemoveAnnotation),
+    ]),
+    MenuEntry::SubEntries('t', "Transform selection", &[
+        MenuEntry::CommandEntry('x', "XOR", HexEditActions::AskTransform(TransformKind::Xor)),
+        MenuEntry::CommandEntry('a', "AND", HexEditActions::AskTransform(TransformKind::And)),
+        MenuEntry::CommandEntry('o', "OR", HexEditActions::AskTransform(TransformKind::Or)),
+        MenuEntry::CommandEntry('n', "NOT", HexEditActions::AskTransform(TransformKind::Not)),
+        MenuEntry::CommandEntry('l', "Shift left", HexEditActions::AskTransform(TransformKind::ShiftLeft)),
+        MenuEntry::CommandEntry('r', "Shift right", HexEditActions::AskTransform(TransformKind::ShiftRight)),
+        MenuEntry::CommandEntry('L', "Rotate left", HexEditActions::AskTransform(TransformKind::RotateLeft)),
+        MenuEntry::CommandEntry('R', "Rotate right", HexEditActions::AskTransform(TransformKind::RotateRight)),
+        MenuEntry::CommandEntry('+', "Add", HexEditActions::AskTransform(TransformKind::Add)),
+        MenuEntry::CommandEntry('-', "Subtract", HexEditActions::AskTransform(TransformKind::Subtract)),
+        MenuEntry::SubEntries('s', "Byte swap", &[
+            MenuEntry::CommandEntry('w', "16-bit words", HexEditActions::ByteSwap(2)),
+            MenuEntry::CommandEntry('d', "32-bit words", HexEditActions::ByteSwap(4)),
+            MenuEntry::CommandEntry('q', "64-bit words", HexEditActions::ByteSwap(8)),
+        ]),
     ]),
 ];
 
-pub struct HexEdit<FS: Filesystem+'static = DefaultFilesystem> {
-    buffer: SplitVec,
+pub struct HexEdit<FS: Filesystem+'static = DefaultFilesystem, B: Buffer+'static = SplitVec> {
+    buffer: B,
     config: Rc<Config<FS>>,
     rect: Rect<isize>,
     /// The cursor position in nibbles
     cursor_nibble_pos: Nibble,
     status_log: Vec<String>,
     show_last_status: bool,
+    show_inspector: bool,
+    show_minimap: bool,
+    minimap: Minimap,
     data_offset: isize,
     row_offset: isize,
     nibble_active: bool,
     selection_start: Option<isize>,
     insert_mode: bool,
+    /// The count typed before a command, such as the step of an increment
+    count_prefix: Option<u64>,
     input: Input,
-    undo_stack: Vec<EditOperation>,
+    history: History<B::Saved>,
+    marks: Marks,
+    annotations: Annotations,
+    /// An annotation that is still being entered by the user
+    pending_annotation: Option<Annotation>,
+    /// The results of the last checksum calculation, so they can be picked to be copied
+    checksums: Vec<(&'static str, Vec<u8>)>,
+    /// The last checksum field that was defined, so it can be recomputed after later edits
+    checksum_fixup: Option<ChecksumFixup>,
+    /// The offsets of the strings in the strings panel, so a picked one can be gone to
+    string_offsets: Vec<usize>,
+    /// The last thing searched for, so the search can be repeated
+    last_needle: Option<Needle>,
     child_widget: Option<(Box<Widget>, RelativeRect<isize>)>,
     cur_path: Option<PathBuf>,
     clipboard: Option<Vec<u8>>,
 
-    signal_receiver: Rc<SignalReceiver<HexEdit<FS>>>,
+    signal_receiver: Rc<SignalReceiver<HexEdit<FS, B>>>,
     _fs: PhantomData<FS>,
 }
 
-impl<FS: Filesystem+'static> HexEdit<FS> {
-    pub fn new() -> HexEdit<FS> {
+impl<FS: Filesystem+'static, B: Buffer+'static> HexEdit<FS, B> {
+    pub fn new() -> HexEdit<FS, B> {
         let (config, err_msg) = match Config::open_default() {
             Ok(config) => (config, None),
             Err(ConfigError::IoError(ref err)) if err.kind() == io::ErrorKind::NotFound =>
@@ -191,7 +264,7 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             Err(err) => (Default::default(), Some(err)),
         };
         let mut h = HexEdit {
-            buffer: SplitVec::new(),
+            buffer: B::new(),
             config: Rc::new(config),
             rect: Default::default(),
             cursor_nibble_pos: Nibble(0),
@@ -199,11 +272,22 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             row_offset: 0,
             status_log: vec!["Press C-/ for help".to_string()],
             show_last_status: true,
+            show_inspector: false,
+            show_minimap: false,
+            minimap: Minimap::new(MinimapMode::Entropy),
             nibble_active: true,
             selection_start: None,
             insert_mode: false,
+            count_prefix: None,
             child_widget: None,
-            undo_stack: Vec::new(),
+            history: History::new(),
+            marks: Marks::new(),
+            annotations: Annotations::new(),
+            pending_annotation: None,
+            checksums: vec![],
+            checksum_fixup: None,
+            string_offsets: vec![],
+            last_needle: None,
             cur_path: None,
             clipboard: None,
             input: Input::new(),
@@ -223,7 +307,11 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.selection_start = None;
         self.insert_mode = false;
         self.child_widget = None;
-        self.undo_stack = Vec::new();
+        self.history = History::new();
+        self.marks = Marks::new();
+        self.annotations = Annotations::new();
+        self.checksum_fixup = None;
+        self.minimap.invalidate();
     }
 
     fn get_linenumber_mode(&self) -> LineNumberMode {
@@ -248,8 +336,36 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.config.line_width.unwrap_or(self.get_bytes_per_row() as u32) as isize
     }
 
+    /// The inspector is only drawn if it leaves enough room for the bytes
+    fn inspector_visible(&self) -> bool {
+        self.show_inspector && self.rect.width >= INSPECTOR_WIDTH * 2
+    }
+
+    /// The minimap is only drawn if it leaves enough room for the bytes
+    fn minimap_visible(&self) -> bool {
+        self.show_minimap && self.rect.width >= MINIMAP_WIDTH * 16
+    }
+
+    /// The part of the screen left of the minimap, where the bytes and the inspector are drawn
+    fn get_panels_rect(&self) -> Rect<isize> {
+        let mut panels_rect = self.rect;
+        if self.minimap_visible() {
+            panels_rect.width = MINIMAP_LAYOUT.get_absolute_to(self.rect).left - self.rect.left;
+        }
+        panels_rect
+    }
+
+    /// The part of the screen the bytes are drawn in, which excludes the side panels
+    fn get_view_rect(&self) -> Rect<isize> {
+        let mut view_rect = self.get_panels_rect();
+        if self.inspector_visible() {
+            view_rect.width = INSPECTOR_LAYOUT.get_absolute_to(view_rect).left - view_rect.left;
+        }
+        view_rect
+    }
+
     fn get_bytes_per_row(&self) -> isize {
-        let byte_width = self.rect.width - self.get_linenumber_width();
+        let byte_width = self.get_view_rect().width - self.get_linenumber_width();
         // The number of cells per byte WITHOUT whitespace is dependent on wether we are showing
         // the ascii bytes or not.
         let cells_per_byte = if self.config.show_ascii { 3 } else { 2 };
@@ -284,7 +400,8 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.get_linenumber_width() as usize + row_offset * 2 + (row_offset / self.config.group_bytes as usize)
     }
 
-    fn draw_line(&self, rb: &mut Frontend, iter: &mut Iterator<Item=(usize, Option<&u8>)>, row: usize) {
+    fn draw_line(&self, rb: &mut Frontend, iter: &mut Iterator<Item=(usize, Option<&u8>)>, row: usize,
+                 annotated: &[Range<usize>]) {
         let nibble_view_start = self.get_linenumber_width() as usize;
         // The value of this is wrong if we are not showing the ascii view
         let byte_view_start = self.nibble_view_column(self.get_bytes_per_row() as usize);
@@ -302,6 +419,8 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             } else {
                 false
             };
+            let in_annotation = maybe_byte.is_some() && annotated.iter().any(|range|
+                range.start <= byte_pos && byte_pos < range.end);
 
             // Now we draw the nibble view
             let hex_chars = if let Some(&byte) = maybe_byte {
@@ -321,6 +440,8 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             }
             let nibble_style = if (!self.nibble_active && at_current_byte) || in_selection {
                 Style::Selection
+            } else if in_annotation {
+                Style::Annotation
             } else {
                 Style::Default
             };
@@ -356,6 +477,8 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
                 // "fake" cursor by dawing a selection square
                 let byte_style = if (self.nibble_active && at_current_byte) || in_selection {
                     Style::Selection
+                } else if in_annotation {
+                    Style::Annotation
                 } else {
                     Style::Default
                 };
@@ -386,6 +509,7 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
     pub fn draw_view(&self, rb: &mut Frontend) {
         let start_iter = self.data_offset as usize;
         let stop_iter = cmp::min(start_iter + self.get_bytes_per_screen() as usize, self.buffer.len());
+        let annotated = self.annotations.ranges_in(start_iter..stop_iter);
 
         let itit = (start_iter..).zip(  // We are zipping the byte position
             self.buffer.iter_range(start_iter..stop_iter)  // With the data at those bytes
@@ -399,7 +523,65 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             let byte_pos = row_iter.peek().unwrap().0;
             self.draw_line_number(rb, row, byte_pos);
 
-            self.draw_line(rb, &mut row_iter, row);
+            self.draw_line(rb, &mut row_iter, row, &annotated);
+        }
+    }
+
+    fn draw_inspector(&self, rb: &mut Frontend) {
+        let area = INSPECTOR_LAYOUT.get_absolute_to(self.get_panels_rect());
+        let clear_line = util::string_with_repeat(' ', area.width as usize);
+        for i in 0..(area.height as usize) {
+            rb.print_style(area.left as usize, area.top as usize + i, Style::Default, &clear_line);
+            rb.print_char_style(area.left as usize, area.top as usize + i, Style::Default, '|');
+        }
+
+        let pos = self.cursor_nibble_pos.to_bytes() as usize;
+        let end = cmp::min(pos + INSPECTOR_DATA_LEN, self.buffer.len());
+        let data = if pos < end { self.buffer.copy_out(pos..end) } else { vec![] };
+
+        let left = area.left as usize + 2;
+        let width = area.width as usize - 2;
+        let title = format!("Inspector @ {}", pos);
+        rb.print_style(left, area.top as usize, Style::MenuTitle, &title);
+
+        let lines = inspect(&data, self.config.little_endian);
+        for (i, &(label, ref value)) in lines.iter().enumerate().take(cmp::max(area.height, 2) as usize - 2) {
+            let row = area.top as usize + 2 + i;
+            if value.is_empty() {
+                rb.print_style(left, row, Style::MenuTitle, label);
+            } else {
+                let line = format!("{:<9}{}", label, value);
+                rb.print_style(left, row, Style::Default, &line[..cmp::min(line.len(), width)]);
+            }
+        }
+    }
+
+    /// Draw the minimap, marking the rows in the viewport and the row of the cursor.
+    fn draw_minimap(&mut self, rb: &mut Frontend) {
+        let area = MINIMAP_LAYOUT.get_absolute_to(self.rect);
+        self.minimap.update(&self.buffer, area.height as usize);
+
+        let len = self.buffer.len();
+        let rows = self.minimap.levels().len();
+        let view_start = self.data_offset as usize;
+        let view_end = cmp::min(view_start + self.get_bytes_per_screen() as usize, len);
+        let cursor_row = Minimap::row_of(len, rows, self.cursor_nibble_pos.to_bytes() as usize);
+
+        let clear_line = util::string_with_repeat(' ', area.width as usize);
+        for i in 0..(area.height as usize) {
+            rb.print_style(area.left as usize, area.top as usize + i, Style::Default, &clear_line);
+        }
+
+        for (row, &level) in self.minimap.levels().iter().enumerate() {
+            let range = Minimap::row_range(len, rows, row);
+            let in_view = range.start < view_end && range.end > view_start;
+            let marker = if row == cursor_row { '>' } else if in_view { '|' } else { ' ' };
+            let style = if in_view { Style::Selection } else { Style::Default };
+            let y = area.top as usize + row;
+            rb.print_char_style(area.left as usize, y, Style::Default, marker);
+            for x in 1..(area.width as usize) {
+                rb.print_char_style(area.left as usize + x, y, style, shade(level));
+            }
         }
     }
 
@@ -409,6 +591,9 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             if let Some(ref status_line) = self.status_log.last() {
                 rb.print_style(0, rb.height() - 1, Style::StatusBar, &status_line);
             }
+        } else if let Some(annotation) = self.annotations.at(self.cursor_nibble_pos.to_bytes() as usize) {
+            rb.print_style(0, rb.height() - 1, Style::StatusBar,
+                &format!("{}: {}", annotation.name, annotation.comment));
         }
 
         let mode = if let Some(_) = self.selection_start {
@@ -426,9 +611,11 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
                 " Start: {} Size: {} Pos: {} {}",
                 selection_start, size, self.cursor_nibble_pos.to_bytes(), mode);
         } else {
+            let truncated = if self.history.is_truncated() { " (truncated)" } else { "" };
             right_status = format!(
-                " Pos: {} Undo: {} {}",
-                self.cursor_nibble_pos.to_bytes(), self.undo_stack.len(), mode);
+                " Pos: {} Undo: {}{} Redo: {} {}",
+                self.cursor_nibble_pos.to_bytes(), self.history.undo_len(), truncated,
+                self.history.redo_len(), mode);
         };
         let (x_pos, start_index) = if rb.width() >= right_status.len() {
             (rb.width() - right_status.len(), 0)
@@ -441,6 +628,14 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
     pub fn draw(&mut self, rb: &mut Frontend) {
         self.draw_view(rb);
 
+        if self.inspector_visible() {
+            self.draw_inspector(rb);
+        }
+
+        if self.minimap_visible() {
+            self.draw_minimap(rb);
+        }
+
         if let Some(&mut (ref mut child_widget, ref layout)) = self.child_widget.as_mut() {
             child_widget.draw(rb, layout.get_absolute_to(self.rect), true);
         }
@@ -459,28 +654,65 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
     }
 
     pub fn open_vec(&mut self, vec: Vec<u8>) {
-        self.buffer = SplitVec::from_vec(vec);
+        self.buffer = B::from_vec(vec);
         self.reset();
     }
 
     pub fn open(&mut self, path: &Path) {
-        let mut v = vec![];
-        if let Err(e) = FS::open(path).and_then(|mut f| f.read_to_end(&mut v)) {
-            self.status(format!("ERROR: {}", e));
-            return;
+        match FS::open_mapped(path) {
+            Ok(mapped) => {
+                self.buffer = B::from_mapped(mapped);
+            }
+            Err(e) => {
+                self.status(format!("ERROR: {}", e));
+                return;
+            }
         }
-        self.buffer = SplitVec::from_vec(v);
         self.cur_path = Some(PathBuf::from(path));
         self.reset();
+
+        if self.config.persist_history {
+            if let Err(e) = self.load_history(path) {
+                self.status(format!("Error loading history: {}", e));
+            }
+        }
+
+        if let Err(e) = self.load_sidecar(path) {
+            self.status(format!("Error loading marks: {}", e));
+        }
     }
 
     pub fn save(&mut self, path: &Path) {
-        let result = FS::save(path)
+        // Replace the file a link points to, rather than the link itself
+        let real_path = match FS::resolve_links(path) {
+            Ok(real_path) => real_path,
+            Err(e) => {
+                self.status(format!("ERROR: {}", e));
+                return;
+            }
+        };
+
+        // The buffer might still be reading from a mapping of the file we are saving to, so we
+        // write to a temporary file first and only then replace the original.
+        let mut tmp_path = real_path.as_os_str().to_owned();
+        tmp_path.push(".rex-save");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let result = FS::save(&tmp_path)
             .and_then(|mut f| self.buffer.iter_slices()
                       .fold(Ok(()), |res, val| res
                             .and_then(|_| f.write_all(val))
                         )
-                    );
+                    )
+            .and_then(|_| match FS::copy_permissions(&real_path, &tmp_path) {
+                // A new file keeps the default permissions
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            })
+            .and_then(|_| FS::rename(&tmp_path, &real_path));
+        if result.is_err() {
+            let _ = FS::remove(&tmp_path);
+        }
 
         match result {
             Ok(_) => {
@@ -488,34 +720,164 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             }
             Err(e) => {
                 self.status(format!("ERROR: {}", e));
+                return;
             }
         }
+
+        if self.config.persist_history {
+            if let Err(e) = self.save_history(path) {
+                self.status(format!("Error saving history: {}", e));
+            }
+        }
+
+        if let Err(e) = self.save_sidecar(path) {
+            self.status(format!("Error saving marks: {}", e));
+        }
+    }
+
+    /// The marks and annotations of a file are saved next to it, in a file with an added ".rex"
+    /// extension.
+    fn sidecar_path(path: &Path) -> PathBuf {
+        let mut sidecar_path = path.as_os_str().to_owned();
+        sidecar_path.push(".rex");
+        PathBuf::from(sidecar_path)
+    }
+
+    fn load_sidecar(&mut self, path: &Path) -> io::Result<()> {
+        let mut f = match FS::open(Self::sidecar_path(path)) {
+            Ok(f) => f,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let (marks, annotations) = try!(read_sidecar(&mut f));
+        self.marks = marks;
+        self.annotations = annotations;
+        Ok(())
+    }
+
+    fn save_sidecar(&mut self, path: &Path) -> io::Result<()> {
+        let sidecar_path = Self::sidecar_path(path);
+        // Don't litter files that never had marks with empty sidecars, but do clear an existing
+        // one if all marks were removed.
+        if self.marks.len() == 0 && self.annotations.len() == 0 && FS::open(&sidecar_path).is_err() {
+            return Ok(());
+        }
+
+        let mut f = try!(FS::save(&sidecar_path));
+        write_sidecar(&mut f, &self.marks, &self.annotations)
+    }
+
+    fn history_key(&self, path: &Path) -> io::Result<HistoryKey> {
+        let abs_path = try!(FS::make_absolute(path));
+        Ok(HistoryKey::new(&abs_path, self.buffer.iter_slices()))
+    }
+
+    /// Restore the edit history saved for the file, if the file did not change since.
+    fn load_history(&mut self, path: &Path) -> io::Result<()> {
+        let key = try!(self.history_key(path));
+        let history_path = match FS::open_data("rex", key.file_name()) {
+            Some(history_path) => history_path,
+            None => return Ok(()),
+        };
+
+        let mut f = try!(FS::open(history_path));
+        if let Some(history) = try!(History::load_from(&mut self.buffer, &key, &mut f)) {
+            self.history = history;
+            self.status("Restored edit history");
+            self.limit_history();
+        }
+        Ok(())
+    }
+
+    fn save_history(&mut self, path: &Path) -> io::Result<()> {
+        let key = try!(self.history_key(path));
+        let mut f = try!(FS::save(try!(FS::save_data("rex", key.file_name()))));
+        self.history.save_to(&self.buffer, &key, &mut f)
     }
 
     /// We pretty much apply the data over the range as a splice, except for when an operation
-    /// exceeds the end of the vector, and then we will cap the range to the length of the vector
-    fn edit_buffer(&mut self, operation: EditOperation, add_to_undo: bool) {
+    /// exceeds the end of the vector, and then we will cap the range to the length of the vector.
+    /// Returns the operation that reverts the applied one.
+    fn apply_operation(&mut self, operation: EditOperation<B::Saved>) -> EditOperation<B::Saved> {
         let begin = operation.range.start;
-        let orig_data = self.buffer.splice(operation.range, &operation.data);
-        if add_to_undo {
-            let undo_operation = EditOperation {
-                range: begin..operation.data.len(),
-                data: orig_data,
-                description: operation.description,
-            };
-            self.push_undo(undo_operation);
+        let end = cmp::min(operation.range.end, self.buffer.len());
+        self.marks.splice(&(begin..end), operation.data.len());
+        self.annotations.splice(&(begin..end), operation.data.len());
+        if let Some(ref mut fixup) = self.checksum_fixup {
+            fixup.splice(&(begin..end), operation.data.len());
+        }
+        self.minimap.invalidate();
+        let orig_data = self.buffer.splice_saved(operation.range, &operation.data);
+        EditOperation {
+            range: begin..(begin + operation.data.len()),
+            data: orig_data,
+            description: operation.description,
+        }
+    }
+
+    /// Apply a new edit to the buffer, which can then be undone. Any undone operations can no
+    /// longer be redone after a new edit.
+    fn edit_buffer(&mut self, operation: EditOperation) {
+        let operation = EditOperation {
+            range: operation.range,
+            data: self.buffer.save(operation.data),
+            description: operation.description,
+        };
+        let undo_operation = self.apply_operation(operation);
+        self.history.push(undo_operation);
+        self.limit_history();
+    }
+
+    /// Drop old undo history once it uses more memory than configured, `undo_memory_limit` is in
+    /// megabytes and 0 means no limit.
+    fn limit_history(&mut self) {
+        if self.config.undo_memory_limit == 0 {
+            return;
+        }
+
+        let limit = self.config.undo_memory_limit as usize * 1024 * 1024;
+        let dropped = self.history.trim(limit);
+        if dropped > 0 {
+            self.status(format!("Undo history over {} MB, dropped {} oldest edits",
+                self.config.undo_memory_limit, dropped));
         }
     }
 
-    fn push_undo(&mut self, operation: EditOperation) {
-        self.undo_stack.push(operation);
+    /// Apply the operations of an undo group, in reverse order, returning the group that reverts
+    /// it and the range of the last applied operation.
+    fn apply_group(&mut self, group: UndoGroup<B::Saved>) -> (UndoGroup<B::Saved>, Range<usize>) {
+        let mut last_range = 0..0;
+        let mut reverted_operations = Vec::with_capacity(group.operations.len());
+        for operation in group.operations.iter().rev() {
+            let reverted = self.apply_operation(operation.clone());
+            last_range = reverted.range.clone();
+            reverted_operations.push(reverted);
+        }
+        (group.reverted(reverted_operations), last_range)
     }
 
     fn undo(&mut self) {
-        if let Some(operation) = self.undo_stack.pop() {
-            let begin = operation.range.start;
-            self.edit_buffer(operation, false);
-            self.set_cursor(Nibble::from_bytes(begin as isize));
+        if let Some(group) = self.history.pop_undo() {
+            let (redo_group, last_range) = self.apply_group(group);
+            self.status(format!("Undid: {}", redo_group.describe()));
+            self.history.push_redo(redo_group);
+            self.limit_history();
+            self.set_cursor(Nibble::from_bytes(last_range.start as isize));
+        } else {
+            self.status("Nothing to undo");
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(group) = self.history.pop_redo() {
+            let (undo_group, last_range) = self.apply_group(group);
+            self.status(format!("Redid: {}", undo_group.describe()));
+            self.history.push_undo(undo_group);
+            self.limit_history();
+            self.set_cursor(Nibble::from_bytes(last_range.end as isize));
+        } else {
+            self.status("Nothing to redo");
         }
     }
 
@@ -556,7 +918,7 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         }
 
         self.selection_start = None;
-        self.edit_buffer(EditOperation::delete(del_start as usize..del_stop as usize), true);
+        self.edit_buffer(EditOperation::delete(del_start as usize..del_stop as usize));
         self.set_cursor(Nibble::from_bytes(del_start));
     }
 
@@ -583,7 +945,7 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         };
 
         let byte_offset = self.cursor_nibble_pos.to_bytes();
-        self.edit_buffer(EditOperation::write(byte_offset as usize, vec![byte]), true);
+        self.edit_buffer(EditOperation::write(byte_offset as usize, vec![byte]));
     }
 
     fn insert_nibble_at_cursor(&mut self, c: u8) {
@@ -594,7 +956,7 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         }
 
         let pos_div2 = self.cursor_nibble_pos.to_bytes();
-        self.edit_buffer(EditOperation::insert(pos_div2 as usize, vec![c * 16]), true);
+        self.edit_buffer(EditOperation::insert(pos_div2 as usize, vec![c * 16]));
     }
 
     fn toggle_insert_mode(&mut self) {
@@ -609,12 +971,45 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
 
         let byte_offset = self.cursor_nibble_pos.to_bytes();
         if self.insert_mode || self.cursor_at_end() {
-            self.edit_buffer(EditOperation::insert(byte_offset as usize, vec![c]), true);
+            self.edit_buffer(EditOperation::insert(byte_offset as usize, vec![c]));
         } else {
-            self.edit_buffer(EditOperation::write(byte_offset as usize, vec![c]), true);
+            self.edit_buffer(EditOperation::write(byte_offset as usize, vec![c]));
         }
     }
 
+    /// Add or subtract the count, or 1 without one, from the integer under the cursor. The integer
+    /// is the group of bytes the cursor is in, read in the configured byte order.
+    fn step_at_cursor(&mut self, count: Option<u64>, increment: bool) {
+        let size = self.config.group_bytes as usize;
+        if size < 1 || size > 8 {
+            self.status("Group size should be between 1 and 8 bytes to increment");
+            return;
+        }
+
+        let pos = self.cursor_nibble_pos.to_bytes() as usize;
+        let start = pos - pos % size;
+        if start + size > self.buffer.len() {
+            self.status("No integer under the cursor");
+            return;
+        }
+
+        let step = match count {
+            None | Some(0) => 1,
+            Some(n) => n,
+        };
+        let little_endian = self.config.little_endian;
+        let val = read_uint(&self.buffer.copy_out(start..start + size), size, little_endian).unwrap();
+        let new_val = step_uint(val, step, size, increment, self.config.saturating_increment);
+
+        let description = if increment { "Increment" } else { "Decrement" };
+        self.edit_buffer(EditOperation {
+            description: description.into(),
+            ..EditOperation::write(start, uint_to_bytes(new_val, size, little_endian))
+        });
+        self.status(format!("{} {} to {}", if increment { "Incremented" } else { "Decremented" },
+            val, new_val));
+    }
+
     fn move_cursor(&mut self, pos: Nibble) {
         self.cursor_nibble_pos = self.cursor_nibble_pos + pos;
         self.update_cursor()
@@ -650,6 +1045,70 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         }
     }
 
+    fn toggle_inspector(&mut self) {
+        self.show_inspector = !self.show_inspector;
+        // The view got narrower or wider, so make sure the cursor is still visible
+        self.update_cursor();
+    }
+
+    fn toggle_minimap(&mut self) {
+        self.show_minimap = !self.show_minimap;
+        self.update_cursor();
+    }
+
+    fn set_minimap_mode(&mut self, mode: MinimapMode) {
+        self.minimap.set_mode(mode);
+        if !self.show_minimap {
+            self.toggle_minimap();
+        }
+    }
+
+    /// Pick a row of the minimap and scroll the view to the start of its block.
+    fn start_minimap_jump(&mut self) {
+        if !self.show_minimap {
+            self.toggle_minimap();
+        }
+        if !self.minimap_visible() {
+            self.status("The screen is too narrow for the minimap");
+            return;
+        }
+
+        let len = self.buffer.len();
+        let rows = Minimap::rows_for(len, MINIMAP_LAYOUT.get_absolute_to(self.rect).height as usize);
+        let mut jump = MinimapJump::new(len, rows, self.cursor_nibble_pos.to_bytes() as usize);
+        let sr = &self.signal_receiver;
+        jump.on_selected.connect(signal!(sr with |obj, pos| {
+            obj.child_widget = None;
+            obj.clear_status();
+            obj.jump_to(pos as isize);
+        }));
+        jump.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+        self.child_widget = Some((Box::new(jump), MINIMAP_LAYOUT));
+        self.status("Pick a spot with Up/Down, Enter jumps to it");
+    }
+
+    /// Scroll the view so that the row of pos is at the top, with the cursor on pos.
+    fn jump_to(&mut self, pos: isize) {
+        self.data_offset = pos - pos % self.get_line_width();
+        self.set_cursor(Nibble::from_bytes(pos));
+    }
+
+    /// The range of bytes in the selection, which includes the byte under the cursor
+    fn selection_range(&self) -> Option<Range<usize>> {
+        self.selection_start.map(|selection_pos| {
+            let cursor_pos = self.cursor_nibble_pos.to_bytes();
+            let end = cmp::min(cmp::max(selection_pos, cursor_pos) + 1, self.buffer.len() as isize);
+            cmp::min(selection_pos, cursor_pos) as usize..end as usize
+        })
+    }
+
     fn toggle_selection(&mut self) {
         match self.selection_start {
             Some(_) => self.selection_start = None,
@@ -662,16 +1121,49 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.set_cursor(Nibble::from_bytes(pos));
     }
 
-    fn find_buf(&mut self, needle: &[u8]) {
-        let found_pos = match self.buffer.find_slice_from(self.cursor_nibble_pos.to_bytes() as usize, needle) {
-            None => {
-                self.buffer.find_slice_from(0, needle)
+    fn find_buf(&mut self, needle: &Needle) {
+        self.last_needle = Some(needle.clone());
+        let from = self.cursor_nibble_pos.to_bytes() as usize;
+        self.search_last_needle(from, true);
+    }
+
+    /// Repeat the last search, after the cursor or before it.
+    fn find_next(&mut self, forward: bool) {
+        if self.last_needle.is_none() {
+            self.status("Nothing to search for, start with C-F");
+            return;
+        }
+        let pos = self.cursor_nibble_pos.to_bytes() as usize;
+        self.search_last_needle(if forward { pos + 1 } else { pos }, forward);
+    }
+
+    /// Search for the last needle forward from a position, or backward before it, wrapping around
+    /// the end of the buffer.
+    fn search_last_needle(&mut self, from: usize, forward: bool) {
+        let (found_pos, wrapped) = {
+            let needle = match self.last_needle {
+                Some(ref needle) => needle,
+                None => return,
+            };
+            let found_pos = if forward {
+                needle.find_from(&self.buffer, from)
+            } else {
+                needle.rfind_from(&self.buffer, from)
+            };
+            match found_pos {
+                None if forward => (needle.find_from(&self.buffer, 0), true),
+                None => (needle.rfind_from(&self.buffer, self.buffer.len()), true),
+                a => (a, false),
             }
-            a => a
         };
 
         if let Some(pos) = found_pos {
-            self.status(format!("Found at {:?}", pos));
+            if wrapped {
+                let edge = if forward { "end" } else { "start" };
+                self.status(format!("Found at {:?}, search wrapped past the {}", pos, edge));
+            } else {
+                self.status(format!("Found at {:?}", pos));
+            }
             self.set_cursor(Nibble::from_bytes(pos as isize));
         } else {
             self.status("Nothing found!");
@@ -703,7 +1195,9 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
 
     fn edit_cut(&mut self) {
         if let Some(data_len) = self.read_cursor_to_clipboard() {
+            self.history.begin_group("Cut");
             self.delete_at_cursor(false);
+            self.history.end_group();
             self.status(format!("Cut {}", data_len));
         }
     }
@@ -718,15 +1212,27 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         let data_len = data.len() as isize;
         // This is needed to satisfy the borrow checker
         let cur_pos_in_bytes = self.cursor_nibble_pos.to_bytes();
+        self.history.begin_group("Paste");
         if self.insert_mode {
-            self.edit_buffer(EditOperation::insert(cur_pos_in_bytes as usize, data), true);
+            self.edit_buffer(EditOperation::insert(cur_pos_in_bytes as usize, data));
         } else {
-            self.edit_buffer(EditOperation::write(cur_pos_in_bytes as usize, data), true);
+            self.edit_buffer(EditOperation::write(cur_pos_in_bytes as usize, data));
         }
+        self.history.end_group();
         self.move_cursor(Nibble::from_bytes(data_len));
     }
 
     fn view_input(&mut self, key: KeyPress) {
+        // Digits typed after the count prefix key make up the count
+        if let (Some(count), KeyPress::Key(c)) = (self.count_prefix, key) {
+            if let Some(digit) = c.to_digit(10) {
+                let count = count.saturating_mul(10).saturating_add(digit as u64);
+                self.count_prefix = Some(count);
+                self.status(format!("Count: {}", count));
+                return;
+            }
+        }
+
         if let Some(action) = self.input.editor_input(key) {
             self.do_action(action)
         }
@@ -734,7 +1240,24 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
 
     fn do_action(&mut self, action: HexEditActions) {
         self.clear_status();
+        let count = self.count_prefix.take();
         match action {
+            HexEditActions::Edit(_) |
+            HexEditActions::Delete |
+            HexEditActions::DeleteWithMove |
+            HexEditActions::CutSelection |
+            HexEditActions::PasteSelection |
+            HexEditActions::AskWriteValue |
+            HexEditActions::AskTransform(_) |
+            HexEditActions::ByteSwap(_) |
+            HexEditActions::AskFill |
+            HexEditActions::AskChecksumFixup |
+            HexEditActions::ApplyChecksumFixup |
+            HexEditActions::Increment |
+            HexEditActions::Decrement |
+            HexEditActions::Undo |
+            HexEditActions::Redo if self.buffer.is_read_only() => self.status("Buffer is read-only"),
+
             // Movement
             HexEditActions::MoveLeft if self.nibble_active => self.move_cursor(Nibble(-1)),
             HexEditActions::MoveRight if self.nibble_active => self.move_cursor(Nibble(1)),
@@ -807,26 +1330,54 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
             HexEditActions::ToggleInsert => self.toggle_insert_mode(),
 
             HexEditActions::ToggleSelecion => self.toggle_selection(),
+            HexEditActions::StartCount => {
+                self.count_prefix = Some(0);
+                self.status("Count: ");
+            }
+            HexEditActions::Increment => self.step_at_cursor(count, true),
+            HexEditActions::Decrement => self.step_at_cursor(count, false),
+            HexEditActions::ToggleInspector => self.toggle_inspector(),
+            HexEditActions::ToggleMinimap => self.toggle_minimap(),
+            HexEditActions::SetMinimapMode(mode) => self.set_minimap_mode(mode),
+            HexEditActions::AskMinimapJump => self.start_minimap_jump(),
 
             HexEditActions::Undo => self.undo(),
+            HexEditActions::Redo => self.redo(),
 
             HexEditActions::AskGoto => self.start_goto(),
             HexEditActions::AskFind => self.start_find(),
+            HexEditActions::FindNext => self.find_next(true),
+            HexEditActions::FindPrevious => self.find_next(false),
+            HexEditActions::AskWriteValue => self.start_write_value(),
+            HexEditActions::AskTransform(kind) => self.start_transform(kind),
+            HexEditActions::ByteSwap(size) => self.byte_swap(size),
+            HexEditActions::AskFill => self.start_fill(count),
+            HexEditActions::ShowChecksums => self.start_checksums(),
+            HexEditActions::ShowStatistics => self.start_statistics(),
+            HexEditActions::ShowStrings => self.start_strings(),
+            HexEditActions::AskChecksumFixup => self.start_checksum_fixup(),
+            HexEditActions::ApplyChecksumFixup => self.apply_checksum_fixup(),
             HexEditActions::AskOpen => self.start_open(),
             HexEditActions::AskSave => self.start_save(),
             HexEditActions::AskConfig => self.start_config(),
+            HexEditActions::AskMarkAdd => self.start_mark_add(),
+            HexEditActions::AskMarkGoto => self.start_mark_goto(),
+            HexEditActions::AskAnnotate => self.start_annotate(),
+            HexEditActions::RemoveAnnotation => self.remove_annotation(),
 
-            HexEditActions::StartMenu => self.start_menu(),
+            HexEditActions::StartMenu => self.start_menu(count),
 
             _ => self.status(format!("Operation not implemented yet: {:?}", action))
         }
     }
 
-    fn start_menu(&mut self) {
+    /// Open the menu, passing the count typed before it on to the selected command
+    fn start_menu(&mut self, count: Option<u64>) {
         let sr = &self.signal_receiver;
         let mut menu = OverlayMenu::with_menu(ROOT_ENTRIES);
         menu.on_selected.connect(signal!(sr with |obj, action| {
             obj.child_widget = None;
+            obj.count_prefix = count;
             obj.do_action(action);
         }));
         menu.on_cancel.connect(signal!(sr with |obj, opt_msg| {
@@ -945,8 +1496,185 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.child_widget = Some((Box::new(InputLine::new(gt)) as Box<Widget>, INPUTLINE_LAYOUT));
     }
 
+    fn start_mark_add(&mut self) {
+        let mut mark_line = TextInputLine::new("Mark name: ", false);
+        let sr = &self.signal_receiver;
+        mark_line.on_done.connect(signal!(sr with |obj, name| {
+            obj.child_widget = None;
+            obj.add_mark(name.clone());
+        }));
+
+        mark_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(mark_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    fn add_mark(&mut self, name: String) {
+        let pos = self.cursor_nibble_pos.to_bytes();
+        if self.marks.set(name.clone(), pos as usize) {
+            self.status(format!("Moved mark {} to {}", name, pos));
+        } else {
+            self.status(format!("Added mark {} at {}", name, pos));
+        }
+    }
+
+    fn start_annotate(&mut self) {
+        let range = match self.selection_range() {
+            Some(range) => range,
+            None => {
+                self.status("Select a range to annotate");
+                return;
+            }
+        };
+        self.pending_annotation = Some(Annotation {
+            name: String::new(),
+            range: range,
+            comment: String::new(),
+        });
+
+        let mut name_line = TextInputLine::new("Annotation name: ", false);
+        let sr = &self.signal_receiver;
+        name_line.on_done.connect(signal!(sr with |obj, name| {
+            obj.child_widget = None;
+            if let Some(ref mut annotation) = obj.pending_annotation {
+                annotation.name = name.clone();
+            }
+            obj.start_annotate_comment();
+        }));
+
+        name_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            obj.pending_annotation = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(name_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    fn start_annotate_comment(&mut self) {
+        let mut comment_line = TextInputLine::new("Comment: ", true);
+        let sr = &self.signal_receiver;
+        comment_line.on_done.connect(signal!(sr with |obj, comment| {
+            obj.child_widget = None;
+            if let Some(mut annotation) = obj.pending_annotation.take() {
+                annotation.comment = comment.clone();
+                obj.status(format!("Annotated {} bytes as {}",
+                    annotation.range.end - annotation.range.start, annotation.name));
+                obj.annotations.add(annotation);
+                obj.selection_start = None;
+            }
+        }));
+
+        comment_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            obj.pending_annotation = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(comment_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    fn remove_annotation(&mut self) {
+        match self.annotations.remove_at(self.cursor_nibble_pos.to_bytes() as usize) {
+            Some(annotation) => self.status(format!("Removed annotation {}", annotation.name)),
+            None => self.status("No annotation at the cursor"),
+        }
+    }
+
+    fn start_mark_goto(&mut self) {
+        if self.marks.len() == 0 {
+            self.status("No marks");
+            return;
+        }
+
+        let items = self.marks.iter().map(|mark| format!("{:>12}  {}", mark.pos, mark.name)).collect();
+        let mut pick_list = PickList::new("Goto mark:".to_string(), items);
+        let sr = &self.signal_receiver;
+        pick_list.on_selected.connect(signal!(sr with |obj, index| {
+            obj.child_widget = None;
+            let pos = obj.marks.get(index).map(|mark| mark.pos);
+            if let Some(pos) = pos {
+                obj.goto(pos as isize);
+            }
+        }));
+        pick_list.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+        self.child_widget = Some((Box::new(pick_list), OVERLAY_LAYOUT));
+    }
+
+    /// Show the checksums of the selection, or of the whole buffer without one. Picking one copies
+    /// it to the clipboard.
+    fn start_checksums(&mut self) {
+        let range = match self.selection_range() {
+            Some(range) => range,
+            None => 0..self.buffer.len(),
+        };
+
+        // Compute all the checksums in a single pass over the data
+        let mut checksums: Vec<Box<Checksum>> = Algorithm::all().iter().map(Algorithm::new_checksum).collect();
+        for slice in self.buffer.iter_range_slices(range.clone()) {
+            for checksum in checksums.iter_mut() {
+                checksum.update(slice);
+            }
+        }
+        self.checksums = Algorithm::all().iter().zip(checksums.iter()).map(|(algorithm, checksum)|
+            (algorithm.name(), checksum.finish())
+        ).collect();
+
+        let items = self.checksums.iter().map(|&(name, ref digest)|
+            format!("{:<9} {}", name, digest.to_hex())
+        ).collect();
+        let title = format!("Checksums of {}..{} (Enter copies):", range.start, range.end);
+        let mut pick_list = PickList::new(title, items);
+        let sr = &self.signal_receiver;
+        pick_list.on_selected.connect(signal!(sr with |obj, index| {
+            obj.child_widget = None;
+            let checksum = obj.checksums.get(index).cloned();
+            if let Some((name, digest)) = checksum {
+                obj.clipboard = Some(digest);
+                obj.status(format!("Copied {} to the clipboard", name));
+            }
+        }));
+        pick_list.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+        self.child_widget = Some((Box::new(pick_list), OVERLAY_LAYOUT));
+    }
+
     fn start_find(&mut self) {
-        let mut find_line = FindInputLine::new();
+        // Values start out like the ones written with start_write_value
+        let size = match self.config.group_bytes {
+            1 | 2 | 4 | 8 => self.config.group_bytes as usize,
+            _ => 4,
+        };
+        let mut find_line = FindInputLine::new(
+            ValueType::new(ValueKind::Unsigned, size, self.config.little_endian));
         let sr = &self.signal_receiver;
         find_line.on_find.connect(signal!(sr with |obj, needle| {
             obj.child_widget = None;
@@ -965,6 +1693,285 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.child_widget = Some((Box::new(InputLine::new(find_line)) as Box<Widget>, INPUTLINE_LAYOUT));
     }
 
+    fn start_write_value(&mut self) {
+        // Start with the integer size of the groups being viewed, if there is one
+        let size = match self.config.group_bytes {
+            1 | 2 | 4 | 8 => self.config.group_bytes as usize,
+            _ => 4,
+        };
+        let mut value_line = WriteValueInputLine::new(
+            ValueType::new(ValueKind::Unsigned, size, self.config.little_endian));
+        let sr = &self.signal_receiver;
+        value_line.on_done.connect(signal!(sr with |obj, bytes| {
+            obj.child_widget = None;
+            obj.write_value(&bytes);
+        }));
+
+        value_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(value_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    /// Write the bytes of a value at the cursor as a single edit, inserting them in insert mode.
+    fn write_value(&mut self, bytes: &[u8]) {
+        let pos = self.cursor_nibble_pos.to_bytes() as usize;
+        let len = bytes.len();
+        let operation = if self.insert_mode || self.cursor_at_end() {
+            EditOperation::insert(pos, bytes.to_vec())
+        } else {
+            EditOperation::write(pos, bytes.to_vec())
+        };
+        self.edit_buffer(EditOperation { description: "Write value".into(), ..operation });
+        self.status(format!("Wrote {} byte{}", len, if len == 1 { "" } else { "s" }));
+    }
+
+    fn start_transform(&mut self, kind: TransformKind) {
+        if self.selection_range().map_or(true, |range| range.start == range.end) {
+            self.status("Select a range to transform");
+            return;
+        }
+
+        // NOT has no argument to ask for
+        if kind == TransformKind::Not {
+            self.transform_selection(&Transform::Not);
+            return;
+        }
+
+        let size = match self.config.group_bytes {
+            1 | 2 | 4 | 8 => self.config.group_bytes as usize,
+            _ => 1,
+        };
+        let mut transform_line = TransformInputLine::new(kind,
+            ValueType::new(ValueKind::Unsigned, size, self.config.little_endian));
+        let sr = &self.signal_receiver;
+        transform_line.on_done.connect(signal!(sr with |obj, transform| {
+            obj.child_widget = None;
+            obj.transform_selection(&transform);
+        }));
+
+        transform_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(transform_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    fn byte_swap(&mut self, size: usize) {
+        if self.selection_range().map_or(true, |range| range.start == range.end) {
+            self.status("Select a range to byte swap");
+            return;
+        }
+        self.transform_selection(&Transform::ByteSwap(size));
+    }
+
+    /// Replace the selection with its transformed bytes, as a single edit.
+    fn transform_selection(&mut self, transform: &Transform) {
+        let range = match self.selection_range() {
+            Some(range) => range,
+            None => return,
+        };
+
+        let mut data = self.buffer.copy_out(range.clone());
+        if let Err(e) = transform.apply(&mut data) {
+            self.status(e);
+            return;
+        }
+
+        let name = transform.name();
+        self.edit_buffer(EditOperation { description: name.into(), ..EditOperation::write(range.start, data) });
+        self.status(format!("{} applied to {} bytes", name, range.len()));
+    }
+
+    /// Show the byte histogram and entropy of the selection, or of the whole buffer without one.
+    fn start_statistics(&mut self) {
+        let range = match self.selection_range() {
+            Some(range) => range,
+            None => 0..self.buffer.len(),
+        };
+        let stats = ByteStats::from_slices(self.buffer.iter_range_slices(range.clone()));
+
+        let sr = &self.signal_receiver;
+        let mut overlay = StatsOverlay::new(stats, range);
+        overlay.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+        self.child_widget = Some((Box::new(overlay), OVERLAY_LAYOUT));
+        self.status("Press Esc to return");
+    }
+
+    /// List the printable strings in the buffer, picking one goes to it.
+    fn start_strings(&mut self) {
+        let min_len = self.config.strings_min_len as usize;
+        let (found, truncated) = find_strings(self.buffer.iter_slices(), min_len, STRINGS_MAX_COUNT);
+        if found.is_empty() {
+            self.status(format!("No strings of at least {} characters", min_len));
+            return;
+        }
+
+        self.string_offsets = found.iter().map(|s| s.offset).collect();
+        let items = found.iter().map(|s| {
+            let encoding = match s.encoding {
+                StringEncoding::Ascii => "A",
+                StringEncoding::Utf16Le => "W",
+            };
+            let ellipsis = if s.len > s.text.len() { "..." } else { "" };
+            format!("{:08x} {} {}{}", s.offset, encoding, s.text, ellipsis)
+        }).collect();
+        let title = format!("{}{} strings of at least {} characters (Enter goes to one):",
+            found.len(), if truncated { "+" } else { "" }, min_len);
+        let mut pick_list = PickList::new(title, items);
+        let sr = &self.signal_receiver;
+        pick_list.on_selected.connect(signal!(sr with |obj, index| {
+            obj.child_widget = None;
+            let offset = obj.string_offsets.get(index).cloned();
+            if let Some(offset) = offset {
+                obj.goto(offset as isize);
+            }
+        }));
+        pick_list.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+        self.child_widget = Some((Box::new(pick_list), OVERLAY_LAYOUT));
+    }
+
+    /// Ask for where the checksum of the selection is stored and how it is computed, then write it
+    /// there. The definition is kept so the checksum can be recomputed after later edits.
+    fn start_checksum_fixup(&mut self) {
+        let (start, end) = match self.selection_range() {
+            Some(range) => (range.start, range.end),
+            None => {
+                self.status("Select the range the checksum covers first");
+                return;
+            }
+        };
+
+        let mut fixup_line = ChecksumFixupInputLine::new(self.config.little_endian);
+        let sr = &self.signal_receiver;
+        fixup_line.on_done.connect(signal!(sr with |obj, algorithm, little_endian, target| {
+            obj.child_widget = None;
+            obj.checksum_fixup = Some(ChecksumFixup {
+                range: start..end,
+                algorithm: algorithm,
+                little_endian: little_endian,
+                target: target,
+            });
+            obj.apply_checksum_fixup();
+        }));
+
+        fixup_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(fixup_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    /// Recompute the defined checksum field and write it to the buffer.
+    fn apply_checksum_fixup(&mut self) {
+        let fixup = match self.checksum_fixup {
+            Some(ref fixup) => fixup.clone(),
+            None => {
+                self.status("No checksum fix-up defined");
+                return;
+            }
+        };
+
+        match fixup.compute(&self.buffer) {
+            Ok(value) => {
+                self.edit_buffer(EditOperation {
+                    description: "Checksum fix-up".into(),
+                    ..EditOperation::write(fixup.target, value)
+                });
+                self.status(format!("Wrote {}", fixup.describe()));
+            }
+            Err(e) => self.status(e),
+        }
+    }
+
+    /// Ask for what to fill the selection with. Without a selection, in insert mode, the count
+    /// (or 1) copies of the pattern are inserted at the cursor instead.
+    fn start_fill(&mut self, count: Option<u64>) {
+        match self.selection_range() {
+            Some(ref range) if range.start == range.end => {
+                self.status("Nothing to fill, the selection is empty");
+                return;
+            }
+            None if !self.insert_mode => {
+                self.status("Select a range to fill, or switch to insert mode to insert copies");
+                return;
+            }
+            _ => (),
+        }
+
+        let count = match count {
+            None | Some(0) => 1,
+            Some(n) if n > MAX_FILL_LEN as u64 => {
+                self.status("Count too large");
+                return;
+            }
+            Some(n) => n as usize,
+        };
+        let mut fill_line = FillInputLine::new();
+        let sr = &self.signal_receiver;
+        fill_line.on_done.connect(signal!(sr with |obj, fill| {
+            obj.child_widget = None;
+            obj.fill(&fill, count);
+        }));
+
+        fill_line.on_cancel.connect(signal!(sr with |obj, opt_msg| {
+            obj.child_widget = None;
+            if let Some(ref msg) = opt_msg {
+                obj.status(msg.clone());
+            } else {
+                obj.clear_status();
+            }
+        }));
+
+        self.child_widget = Some((Box::new(InputLine::new(fill_line)) as Box<Widget>, INPUTLINE_LAYOUT));
+    }
+
+    fn fill(&mut self, fill: &Fill, count: usize) {
+        let operation = match self.selection_range() {
+            Some(range) => EditOperation::write(range.start, fill.bytes(range.len())),
+            None => match fill.copies(count) {
+                Some(data) => EditOperation::insert(self.cursor_nibble_pos.to_bytes() as usize, data),
+                None => {
+                    self.status("Count too large");
+                    return;
+                }
+            },
+        };
+        let len = operation.data.len();
+        self.edit_buffer(EditOperation { description: "Fill".into(), ..operation });
+        self.status(format!("Filled {} bytes", len));
+    }
+
     fn start_save(&mut self) {
         let mut path_line: PathInputLine<FS> = PathInputLine::new(PathInputType::Save);
         let sr = &self.signal_receiver;
@@ -1032,6 +2039,10 @@ impl<FS: Filesystem+'static> HexEdit<FS> {
         self.cursor_nibble_pos.to_bytes()
     }
 
+    pub fn get_last_status(&mut self) -> Option<&str> {
+        self.status_log.last().map(|s| s.as_str())
+    }
+
     pub fn get_file_path(&mut self) -> Option<&Path> {
         match self.cur_path {
             Some(ref p) => Some(p.as_path()),
@@ -0,0 +1,412 @@
+//! A common interface over the containers that can hold the data being edited.
+
+use std::cmp;
+use std::fmt;
+use std::ops;
+use std::ops::Range;
+
+use super::split_vec::SplitVec;
+use super::piece_table::{PieceTable, Snapshot};
+
+/// Iterating backward collects the slices of this much data at a time
+const REV_WINDOW: usize = 0x10000;
+
+/// Data that a buffer saved so that it can be spliced back in later, such as the data replaced by
+/// an edit, which is kept to undo it.
+pub trait SavedData: Clone + fmt::Debug {
+    /// Return the length of the data.
+    fn len(&self) -> usize;
+
+    /// Return the amount of memory used to keep the data.
+    fn memory(&self) -> usize;
+}
+
+impl SavedData for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn memory(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+/// The operations an editor needs from the container holding its data.
+///
+/// Only the basic operations need to be implemented, range iteration, copying and searching are
+/// implemented on top of `iter_slices`, though implementors are encouraged to provide faster
+/// versions of them.
+pub trait Buffer: Sized + ops::Index<usize, Output=u8> {
+    /// The form in which the buffer saves data, see `splice_saved`. Buffers that modify their
+    /// data in place simply save a copy of it.
+    type Saved: SavedData;
+
+    /// Create a new, empty buffer
+    fn new() -> Self;
+
+    /// Create a buffer by consuming a vec as the initial data
+    fn from_vec(values: Vec<u8>) -> Self;
+
+    /// Create a buffer over a read-only source, such as a memory mapped file
+    fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> Self;
+
+    /// Return the length.
+    fn len(&self) -> usize;
+
+    /// Provide an iterator over continuous memory slices, in order, covering the whole buffer
+    fn iter_slices<'a>(&'a self) -> Box<Iterator<Item=&'a [u8]> + 'a>;
+
+    /// Replace values in range with the supplied values, returning the values that were replaced
+    fn splice(&mut self, range: Range<usize>, values: &[u8]) -> Vec<u8>;
+
+    /// Save values so that they can be spliced in with `splice_saved`
+    fn save(&mut self, values: Vec<u8>) -> Self::Saved;
+
+    /// Replace values in range with saved values, returning the values that were replaced in
+    /// saved form
+    fn splice_saved(&mut self, range: Range<usize>, values: &Self::Saved) -> Self::Saved;
+
+    /// Produce a copy of saved values
+    fn copy_saved(&self, values: &Self::Saved) -> Vec<u8>;
+
+    /// Returns true if the buffer does not allow modifications, in which case `splice` should not
+    /// be called.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Give an iterator over the parts of the continuous memory slices that are in a given range
+    fn iter_range_slices<'a>(&'a self, range: Range<usize>) -> Box<Iterator<Item=&'a [u8]> + 'a> {
+        let mut slice_start = 0;
+        Box::new(self.iter_slices().filter_map(move |slice| {
+            let slice_end = slice_start + slice.len();
+            let res = if slice_end <= range.start || slice_start >= range.end {
+                None
+            } else {
+                Some(&slice[range.start.saturating_sub(slice_start)..
+                            cmp::min(range.end, slice_end) - slice_start])
+            };
+            slice_start = slice_end;
+            res
+        }))
+    }
+
+    /// Give an iterator over a given range
+    fn iter_range<'a>(&'a self, range: Range<usize>) -> Box<Iterator<Item=&'a u8> + 'a> {
+        Box::new(self.iter_range_slices(range).flat_map(|slice| slice.iter()))
+    }
+
+    /// Give an iterator over a given range, from its end to its start
+    ///
+    /// The range is gone over a window at a time, so only the data that is iterated over is read.
+    fn iter_range_rev<'a>(&'a self, range: Range<usize>) -> Box<Iterator<Item=&'a u8> + 'a> {
+        let (start, end) = (range.start, range.end);
+        let num_windows = (end.saturating_sub(start) + REV_WINDOW - 1) / REV_WINDOW;
+        Box::new((0..num_windows).flat_map(move |i| {
+            let window_end = end - i * REV_WINDOW;
+            let window_start = cmp::max(start, window_end.saturating_sub(REV_WINDOW));
+            let slices: Vec<&'a [u8]> = self.iter_range_slices(window_start..window_end).collect();
+            slices.into_iter().rev().flat_map(|slice| slice.iter().rev())
+        }))
+    }
+
+    /// Produce of copy of the supplied range
+    fn copy_out(&self, range: Range<usize>) -> Vec<u8> {
+        self.iter_range(range).cloned().collect()
+    }
+
+    /// Find a slice from a certain index and onward
+    fn find_slice_from(&self, from: usize, needle: &[u8]) -> Option<usize> {
+        if from >= self.len() {
+            return None;
+        }
+        find_in_iter(self.iter_range(from..self.len()), needle).map(|pos| from + pos)
+    }
+
+    /// Find bytes that match a needle in the bits set in mask, from a certain index and onward
+    fn find_masked_from(&self, from: usize, needle: &[u8], mask: &[u8]) -> Option<usize> {
+        if from >= self.len() {
+            return None;
+        }
+        find_masked_in_iter(self.iter_range(from..self.len()), needle, mask).map(|pos| from + pos)
+    }
+
+    /// Find the last occurrence of a slice that starts before a certain index
+    fn rfind_slice_from(&self, from: usize, needle: &[u8]) -> Option<usize> {
+        let end = match rfind_end(self.len(), from, needle.len()) {
+            Some(end) => end,
+            None => return None,
+        };
+        let reversed: Vec<u8> = needle.iter().rev().cloned().collect();
+        find_in_iter(self.iter_range_rev(0..end), &reversed).map(|pos| end - pos - needle.len())
+    }
+
+    /// Find the last bytes that match a needle in the bits set in mask, starting before a certain
+    /// index
+    fn rfind_masked_from(&self, from: usize, needle: &[u8], mask: &[u8]) -> Option<usize> {
+        self.rfind_any_masked_from(from, &[(needle, mask)])
+    }
+
+    /// Find the last bytes that match any of several masked needles of the same length, starting
+    /// before a certain index
+    fn rfind_any_masked_from(&self, from: usize, needles: &[(&[u8], &[u8])]) -> Option<usize> {
+        let len = match needles.first() {
+            Some(&(needle, _)) => needle.len(),
+            None => return None,
+        };
+        let end = match rfind_end(self.len(), from, len) {
+            Some(end) => end,
+            None => return None,
+        };
+        let reversed: Vec<(Vec<u8>, Vec<u8>)> = needles.iter().map(|&(needle, mask)|
+            (needle.iter().rev().cloned().collect(), mask.iter().rev().cloned().collect())
+        ).collect();
+        let reversed: Vec<(&[u8], &[u8])> = reversed.iter().map(|&(ref needle, ref mask)|
+            (&needle[..], &mask[..])
+        ).collect();
+        find_any_masked_in_iter(self.iter_range_rev(0..end), &reversed).map(|pos| end - pos - len)
+    }
+}
+
+/// The end of the data to search backward through for a needle of needle_len bytes that starts
+/// before from, or None if there is no room for one.
+fn rfind_end(buffer_len: usize, from: usize, needle_len: usize) -> Option<usize> {
+    if from == 0 {
+        return None;
+    }
+    let end = cmp::min(from - 1 + needle_len, buffer_len);
+    if end < needle_len { None } else { Some(end) }
+}
+
+/// Find the first occurrence of needle in the bytes returned by an iterator, returning its offset
+/// from the start of the iterator.
+///
+/// The search is done in a single pass over the data (using Knuth-Morris-Pratt), so that buffers
+/// that are large or not in memory are only read once.
+///
+/// # Examples
+///
+/// ```
+/// use rex::util::buffer::find_in_iter;
+///
+/// let haystack = [1, 2, 1, 2, 3];
+/// assert_eq!(find_in_iter(haystack.iter(), &[1, 2, 3]), Some(2));
+/// assert_eq!(find_in_iter(haystack.iter(), &[3, 1]), None);
+/// ```
+pub fn find_in_iter<'a, I: Iterator<Item=&'a u8>>(iter: I, needle: &[u8]) -> Option<usize> {
+    if needle.len() == 0 {
+        return Some(0);
+    }
+
+    // The failure table, for each prefix of the needle the length of the longest proper prefix
+    // that is also a suffix of it.
+    let mut failure = vec![0; needle.len()];
+    let mut k = 0;
+    for i in 1..needle.len() {
+        while k > 0 && needle[i] != needle[k] {
+            k = failure[k - 1];
+        }
+        if needle[i] == needle[k] {
+            k += 1;
+        }
+        failure[i] = k;
+    }
+
+    let mut matched = 0;
+    for (i, &b) in iter.enumerate() {
+        while matched > 0 && b != needle[matched] {
+            matched = failure[matched - 1];
+        }
+        if b == needle[matched] {
+            matched += 1;
+        }
+        if matched == needle.len() {
+            return Some(i + 1 - needle.len());
+        }
+    }
+    None
+}
+
+/// Find the first bytes in an iterator that match a needle in the bits that are set in the mask,
+/// returning their offset from the start of the iterator. The needle and the mask should be the
+/// same length, and the needle shouldn't have bits set outside of the mask.
+///
+/// The data is read once, through a window of the needle's length.
+///
+/// # Examples
+///
+/// ```
+/// use rex::util::buffer::find_masked_in_iter;
+///
+/// let haystack = [0x12, 0xde, 0x00, 0x47, 0xef];
+/// assert_eq!(find_masked_in_iter(haystack.iter(), &[0xde, 0x00, 0x40], &[0xff, 0x00, 0xf0]), Some(1));
+/// assert_eq!(find_masked_in_iter(haystack.iter(), &[0xde, 0x50], &[0xff, 0xf0]), None);
+/// ```
+pub fn find_masked_in_iter<'a, I: Iterator<Item=&'a u8>>(iter: I, needle: &[u8], mask: &[u8]) -> Option<usize> {
+    find_any_masked_in_iter(iter, &[(needle, mask)])
+}
+
+/// Like `find_masked_in_iter`, but finds the first bytes that match any of several masked needles,
+/// which should all be the same length.
+///
+/// The needles are grouped by the value of their most fixed byte, so that at each offset only the
+/// needles that can match the byte there are tested, rather than all of them.
+///
+/// # Examples
+///
+/// ```
+/// use rex::util::buffer::find_any_masked_in_iter;
+///
+/// let haystack = [0x12, 0x34, 0x56];
+/// let needles: [(&[u8], &[u8]); 2] = [(&[0x56], &[0xff]), (&[0x30], &[0xf0])];
+/// assert_eq!(find_any_masked_in_iter(haystack.iter(), &needles), Some(1));
+/// ```
+pub fn find_any_masked_in_iter<'a, I>(iter: I, needles: &[(&[u8], &[u8])]) -> Option<usize>
+        where I: Iterator<Item=&'a u8> {
+    let len = match needles.first() {
+        Some(&(needle, _)) => needle.len(),
+        None => return None,
+    };
+    for &(needle, mask) in needles {
+        assert!(needle.len() == len && mask.len() == len);
+    }
+    if len == 0 {
+        return Some(0);
+    }
+
+    // Each needle is keyed by the byte with the most bits set in its mask. For each key position
+    // in use, a table from the byte value there to the needles that can match it.
+    let mut tables: Vec<(usize, Vec<Vec<usize>>)> = vec![];
+    for (n, &(needle, mask)) in needles.iter().enumerate() {
+        let key = (0..len).rev().max_by_key(|&j| mask[j].count_ones()).unwrap();
+        if !tables.iter().any(|&(table_key, _)| table_key == key) {
+            tables.push((key, vec![vec![]; 256]));
+        }
+        let table = &mut tables.iter_mut().find(|&&mut (table_key, _)| table_key == key).unwrap().1;
+        for value in 0..256 {
+            if value as u8 & mask[key] == needle[key] {
+                table[value].push(n);
+            }
+        }
+    }
+
+    // A ring of the last len bytes, the oldest of them is at window[i % len]
+    let mut window = vec![0; len];
+    for (i, &b) in iter.enumerate() {
+        window[i % len] = b;
+        if i + 1 < len {
+            continue;
+        }
+        let start = i + 1 - len;
+        let matches = tables.iter().any(|&(key, ref table)|
+            table[window[(start + key) % len] as usize].iter().any(|&n| {
+                let (needle, mask) = needles[n];
+                (0..len).all(|j| window[(start + j) % len] & mask[j] == needle[j])
+            })
+        );
+        if matches {
+            return Some(start);
+        }
+    }
+    None
+}
+
+impl Buffer for SplitVec {
+    type Saved = Vec<u8>;
+
+    fn new() -> SplitVec {
+        SplitVec::new()
+    }
+
+    fn from_vec(values: Vec<u8>) -> SplitVec {
+        SplitVec::from_vec(values)
+    }
+
+    fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> SplitVec {
+        SplitVec::from_mapped(source)
+    }
+
+    fn len(&self) -> usize {
+        SplitVec::len(self)
+    }
+
+    fn iter_range<'a>(&'a self, range: Range<usize>) -> Box<Iterator<Item=&'a u8> + 'a> {
+        Box::new(SplitVec::iter_range(self, range))
+    }
+
+    fn iter_slices<'a>(&'a self) -> Box<Iterator<Item=&'a [u8]> + 'a> {
+        Box::new(SplitVec::iter_slices(self))
+    }
+
+    fn copy_out(&self, range: Range<usize>) -> Vec<u8> {
+        SplitVec::copy_out(self, range)
+    }
+
+    fn splice(&mut self, range: Range<usize>, values: &[u8]) -> Vec<u8> {
+        SplitVec::splice(self, range, values)
+    }
+
+    fn save(&mut self, values: Vec<u8>) -> Vec<u8> {
+        values
+    }
+
+    fn splice_saved(&mut self, range: Range<usize>, values: &Vec<u8>) -> Vec<u8> {
+        SplitVec::splice(self, range, values)
+    }
+
+    fn copy_saved(&self, values: &Vec<u8>) -> Vec<u8> {
+        values.clone()
+    }
+
+    fn find_masked_from(&self, from: usize, needle: &[u8], mask: &[u8]) -> Option<usize> {
+        SplitVec::find_masked_from(self, from, needle, mask)
+    }
+}
+
+impl Buffer for PieceTable {
+    type Saved = Snapshot;
+
+    fn new() -> PieceTable {
+        PieceTable::new()
+    }
+
+    fn from_vec(values: Vec<u8>) -> PieceTable {
+        PieceTable::from_vec(values)
+    }
+
+    fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> PieceTable {
+        PieceTable::from_mapped(source)
+    }
+
+    fn len(&self) -> usize {
+        PieceTable::len(self)
+    }
+
+    fn iter_range<'a>(&'a self, range: Range<usize>) -> Box<Iterator<Item=&'a u8> + 'a> {
+        Box::new(PieceTable::iter_range(self, range))
+    }
+
+    fn iter_slices<'a>(&'a self) -> Box<Iterator<Item=&'a [u8]> + 'a> {
+        Box::new(PieceTable::iter_slices(self))
+    }
+
+    fn copy_out(&self, range: Range<usize>) -> Vec<u8> {
+        PieceTable::copy_out(self, range)
+    }
+
+    fn splice(&mut self, range: Range<usize>, values: &[u8]) -> Vec<u8> {
+        PieceTable::splice(self, range, values)
+    }
+
+    fn save(&mut self, values: Vec<u8>) -> Snapshot {
+        PieceTable::add(self, &values)
+    }
+
+    fn splice_saved(&mut self, range: Range<usize>, values: &Snapshot) -> Snapshot {
+        PieceTable::splice_snapshot(self, range, values)
+    }
+
+    fn copy_saved(&self, values: &Snapshot) -> Vec<u8> {
+        PieceTable::copy_snapshot(self, values)
+    }
+}
@@ -0,0 +1,503 @@
+//! Checksums and hashes that can be computed incrementally over the slices of a buffer.
+
+/// A checksum being computed over data that is given in parts.
+pub trait Checksum {
+    /// Add the next part of the data
+    fn update(&mut self, data: &[u8]);
+
+    /// Get the result over all the data so far, as the bytes of the (big endian) digest
+    fn finish(&self) -> Vec<u8>;
+}
+
+/// The supported checksum and hash algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Crc32,
+    /// CRC-16/ARC, as used by Modbus and many firmware formats
+    Crc16,
+    Adler32,
+    Md5,
+    Sha1,
+    Sha256,
+    /// The sum of the bytes, truncated to 8, 16 or 32 bits
+    Sum8,
+    Sum16,
+    Sum32,
+}
+
+impl Algorithm {
+    /// All the algorithms, in the order they are usually shown
+    pub fn all() -> &'static [Algorithm] {
+        static ALL: [Algorithm; 9] = [
+            Algorithm::Crc32, Algorithm::Crc16, Algorithm::Adler32, Algorithm::Md5, Algorithm::Sha1,
+            Algorithm::Sha256, Algorithm::Sum8, Algorithm::Sum16, Algorithm::Sum32,
+        ];
+        &ALL
+    }
+
+    /// The algorithms that are commonly embedded in data as a checksum field
+    pub fn fields() -> &'static [Algorithm] {
+        static FIELDS: [Algorithm; 5] = [
+            Algorithm::Crc32, Algorithm::Crc16, Algorithm::Sum8, Algorithm::Sum16, Algorithm::Sum32,
+        ];
+        &FIELDS
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Algorithm::Crc32 => "CRC32",
+            Algorithm::Crc16 => "CRC16",
+            Algorithm::Adler32 => "Adler-32",
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sum8 => "Sum8",
+            Algorithm::Sum16 => "Sum16",
+            Algorithm::Sum32 => "Sum32",
+        }
+    }
+
+    pub fn new_checksum(&self) -> Box<Checksum> {
+        match *self {
+            Algorithm::Crc32 => Box::new(Crc32::new()),
+            Algorithm::Crc16 => Box::new(Crc16::new()),
+            Algorithm::Adler32 => Box::new(Adler32::new()),
+            Algorithm::Md5 => Box::new(Md5::new()),
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sum8 => Box::new(Sum::new(1)),
+            Algorithm::Sum16 => Box::new(Sum::new(2)),
+            Algorithm::Sum32 => Box::new(Sum::new(4)),
+        }
+    }
+}
+
+/// Compute a checksum over all the slices.
+///
+/// # Examples
+///
+/// ```
+/// use rex::util::checksum::{Algorithm, checksum};
+///
+/// let data: [&[u8]; 2] = [b"1234", b"56789"];
+/// assert_eq!(checksum(Algorithm::Crc32, data.iter().cloned()), [0xcb, 0xf4, 0x39, 0x26]);
+/// ```
+pub fn checksum<'a, I: Iterator<Item=&'a [u8]>>(algorithm: Algorithm, slices: I) -> Vec<u8> {
+    let mut c = algorithm.new_checksum();
+    for slice in slices {
+        c.update(slice);
+    }
+    c.finish()
+}
+
+/// The standard CRC-32 (as used by zlib, PNG and Ethernet)
+pub struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = (0..8).fold(i as u32, |c, _|
+                if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 });
+        }
+        Crc32 {
+            table: table,
+            crc: 0xffffffff,
+        }
+    }
+}
+
+impl Checksum for Crc32 {
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.crc = self.table[((self.crc ^ b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        be_bytes_u32(&[!self.crc])
+    }
+}
+
+/// CRC-16/ARC, the reflected CRC with the 0x8005 polynomial and no initial or final xor
+pub struct Crc16 {
+    crc: u16,
+}
+
+impl Crc16 {
+    pub fn new() -> Crc16 {
+        Crc16 { crc: 0 }
+    }
+}
+
+impl Checksum for Crc16 {
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.crc = (0..8).fold(self.crc ^ b as u16, |c, _|
+                if c & 1 != 0 { 0xa001 ^ (c >> 1) } else { c >> 1 });
+        }
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        vec![(self.crc >> 8) as u8, self.crc as u8]
+    }
+}
+
+/// The sum of the bytes, truncated to `size` bytes
+pub struct Sum {
+    size: usize,
+    sum: u32,
+}
+
+impl Sum {
+    pub fn new(size: usize) -> Sum {
+        Sum { size: size, sum: 0 }
+    }
+}
+
+impl Checksum for Sum {
+    fn update(&mut self, data: &[u8]) {
+        self.sum = data.iter().fold(self.sum, |sum, &b| sum.wrapping_add(b as u32));
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        let bytes = be_bytes_u32(&[self.sum]);
+        bytes[4 - self.size..].to_vec()
+    }
+}
+
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+}
+
+impl Checksum for Adler32 {
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.a = (self.a + byte as u32) % 65521;
+            self.b = (self.b + self.a) % 65521;
+        }
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        be_bytes_u32(&[(self.b << 16) | self.a])
+    }
+}
+
+fn be_bytes_u32(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| (0..4).rev().map(move |i| (w >> (i * 8)) as u8)).collect()
+}
+
+/// Splits data into the 64 byte blocks the MD5 and SHA hashes work on.
+struct BlockBuffer {
+    pending: Vec<u8>,
+    total_len: u64,
+}
+
+impl BlockBuffer {
+    fn new() -> BlockBuffer {
+        BlockBuffer {
+            pending: Vec::with_capacity(64),
+            total_len: 0,
+        }
+    }
+
+    fn update<F: FnMut(&[u8])>(&mut self, mut data: &[u8], mut process: F) {
+        self.total_len += data.len() as u64;
+        if !self.pending.is_empty() {
+            let needed = 64 - self.pending.len();
+            if data.len() < needed {
+                self.pending.extend_from_slice(data);
+                return;
+            }
+            self.pending.extend_from_slice(&data[..needed]);
+            process(&self.pending);
+            self.pending.clear();
+            data = &data[needed..];
+        }
+        for block in data.chunks(64) {
+            if block.len() == 64 {
+                process(block);
+            } else {
+                self.pending.extend_from_slice(block);
+            }
+        }
+    }
+
+    /// Process the rest of the data, padded with a 1 bit, zeros, and the length in bits in the
+    /// given byte order.
+    fn finish<F: FnMut(&[u8])>(&self, little_endian: bool, mut process: F) {
+        let bit_len = self.total_len.wrapping_mul(8);
+        let mut tail = self.pending.clone();
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        for i in 0..8 {
+            let shift = if little_endian { i * 8 } else { (7 - i) * 8 };
+            tail.push((bit_len >> shift) as u8);
+        }
+        for block in tail.chunks(64) {
+            process(block);
+        }
+    }
+}
+
+pub struct Md5 {
+    state: [u32; 4],
+    blocks: BlockBuffer,
+}
+
+const MD5_SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// The round constants, the integer parts of abs(sin(i + 1)) * 2^32
+const MD5_K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+impl Md5 {
+    pub fn new() -> Md5 {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            blocks: BlockBuffer::new(),
+        }
+    }
+
+    fn process(state: &mut [u32; 4], block: &[u8]) {
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = (0..4).fold(0, |w, j| w | (block[i * 4 + j] as u32) << (j * 8));
+        }
+
+        let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let shift = MD5_SHIFTS[(i / 16) * 4 + i % 4];
+            let new_b = b.wrapping_add(
+                a.wrapping_add(f).wrapping_add(MD5_K[i]).wrapping_add(m[g]).rotate_left(shift));
+            a = d;
+            d = c;
+            c = b;
+            b = new_b;
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+}
+
+impl Checksum for Md5 {
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| Md5::process(state, block));
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        let mut state = self.state;
+        self.blocks.finish(true, |block| Md5::process(&mut state, block));
+        state.iter().flat_map(|w| (0..4).map(move |i| (w >> (i * 8)) as u8)).collect()
+    }
+}
+
+fn be_words(block: &[u8], words: &mut [u32]) {
+    for (i, word) in words.iter_mut().enumerate().take(16) {
+        *word = (0..4).fold(0, |w, j| (w << 8) | block[i * 4 + j] as u32);
+    }
+}
+
+pub struct Sha1 {
+    state: [u32; 5],
+    blocks: BlockBuffer,
+}
+
+impl Sha1 {
+    pub fn new() -> Sha1 {
+        Sha1 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0],
+            blocks: BlockBuffer::new(),
+        }
+    }
+
+    fn process(state: &mut [u32; 5], block: &[u8]) {
+        let mut w = [0u32; 80];
+        be_words(block, &mut w);
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (state[0], state[1], state[2], state[3], state[4]);
+        for i in 0..80 {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(w[i]);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+            *s = s.wrapping_add(*v);
+        }
+    }
+}
+
+impl Checksum for Sha1 {
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| Sha1::process(state, block));
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        let mut state = self.state;
+        self.blocks.finish(false, |block| Sha1::process(&mut state, block));
+        be_bytes_u32(&state)
+    }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub struct Sha256 {
+    state: [u32; 8],
+    blocks: BlockBuffer,
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        Sha256 {
+            state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+            blocks: BlockBuffer::new(),
+        }
+    }
+
+    fn process(state: &mut [u32; 8], block: &[u8]) {
+        let mut w = [0u32; 64];
+        be_words(block, &mut w);
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let mut v = *state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let temp1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(SHA256_K[i]).wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let temp2 = s0.wrapping_add(maj);
+
+            v[7] = v[6];
+            v[6] = v[5];
+            v[5] = v[4];
+            v[4] = v[3].wrapping_add(temp1);
+            v[3] = v[2];
+            v[2] = v[1];
+            v[1] = v[0];
+            v[0] = temp1.wrapping_add(temp2);
+        }
+
+        for (s, v) in state.iter_mut().zip(v.iter()) {
+            *s = s.wrapping_add(*v);
+        }
+    }
+}
+
+impl Checksum for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| Sha256::process(state, block));
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        let mut state = self.state;
+        self.blocks.finish(false, |block| Sha256::process(&mut state, block));
+        be_bytes_u32(&state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rustc_serialize::hex::ToHex;
+
+    fn hex_checksum(algorithm: Algorithm, data: &[u8]) -> String {
+        checksum(algorithm, data.chunks(7)).to_hex()
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(hex_checksum(Algorithm::Crc32, b""), "00000000");
+        assert_eq!(hex_checksum(Algorithm::Adler32, b""), "00000001");
+        assert_eq!(hex_checksum(Algorithm::Md5, b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex_checksum(Algorithm::Sha1, b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex_checksum(Algorithm::Sha256, b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn test_known_values() {
+        let data = b"The quick brown fox jumps over the lazy dog";
+        assert_eq!(hex_checksum(Algorithm::Crc32, data), "414fa339");
+        assert_eq!(hex_checksum(Algorithm::Adler32, b"Wikipedia"), "11e60398");
+        assert_eq!(hex_checksum(Algorithm::Crc16, b"123456789"), "bb3d");
+        assert_eq!(hex_checksum(Algorithm::Sum8, b"123456789"), "dd");
+        assert_eq!(hex_checksum(Algorithm::Sum16, b"123456789"), "01dd");
+        assert_eq!(hex_checksum(Algorithm::Sum32, b"123456789"), "000001dd");
+        assert_eq!(hex_checksum(Algorithm::Md5, data), "9e107d9d372bb6826bd81d3542a419d6");
+        assert_eq!(hex_checksum(Algorithm::Sha1, data), "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
+        assert_eq!(hex_checksum(Algorithm::Sha256, data),
+            "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592");
+    }
+
+    #[test]
+    fn test_block_boundaries() {
+        // Padding that needs an extra block, and data split over blocks unevenly
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        for &len in [55, 56, 63, 64, 65, 200].iter() {
+            let whole = checksum(Algorithm::Sha256, Some(&data[..len]).into_iter());
+            let parts = checksum(Algorithm::Sha256, data[..len].chunks(13));
+            assert_eq!(whole, parts);
+        }
+        assert_eq!(hex_checksum(Algorithm::Md5, &data[..56]), checksum(Algorithm::Md5, data[..56].chunks(64)).to_hex());
+    }
+}
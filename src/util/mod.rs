@@ -2,9 +2,14 @@ use std::iter;
 use std::char;
 
 pub mod iter_optional;
+pub mod buffer;
 pub mod split_vec;
+pub mod piece_table;
 pub mod rect;
 pub mod relative_rect;
+pub mod checksum;
+pub mod strings;
+pub mod regex;
 #[macro_use] pub mod signals;
 
 /// Create a string with a repeated character.
@@ -0,0 +1,409 @@
+//! Provides a piece table, a buffer that keeps its original data untouched.
+
+use std::cmp;
+use std::fmt;
+use std::mem;
+use std::ops;
+use std::ops::Range;
+use std::rc::Rc;
+
+use super::buffer::SavedData;
+
+/// Which of the two backing buffers a piece points into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Source {
+    /// The original, read-only data.
+    Original,
+    /// The append-only buffer holding all data that was ever inserted.
+    Added,
+}
+
+/// A continuous run of data from one of the backing buffers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+/// Data saved from a PieceTable, as the pieces it is made of.
+///
+/// The backing buffers of a table are never modified, so the pieces stay valid after the table
+/// is edited, and saving data never copies it.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pieces: Vec<Piece>,
+    length: usize,
+}
+
+impl SavedData for Snapshot {
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn memory(&self) -> usize {
+        self.pieces.len() * mem::size_of::<Piece>()
+    }
+}
+
+/// A buffer implemented as a piece table.
+///
+/// The original data is never modified, and all inserted data is appended to a second buffer.
+/// The contents of the buffer are described by a list of pieces, each pointing to a run of data
+/// in one of the two buffers. Inserting and deleting only touches the list of pieces, so the
+/// cost of an edit depends on the number of pieces rather than on the amount of data moved.
+pub struct PieceTable {
+    original: Rc<AsRef<[u8]>>,
+    added: Vec<u8>,
+    pieces: Vec<Piece>,
+    length: usize,
+}
+
+/// An iterator over PieceTable contents.
+pub struct Items<'a> {
+    table: &'a PieceTable,
+    piece: usize,
+    inner: usize,
+    num_elem: usize,
+}
+
+/// An iterator over the continuous runs of data in a PieceTable.
+pub struct Slices<'a> {
+    table: &'a PieceTable,
+    piece: usize,
+}
+
+impl PieceTable {
+    /// Create a new, empty PieceTable
+    pub fn new() -> PieceTable {
+        PieceTable::from_mapped(Vec::new())
+    }
+
+    /// Create a PieceTable by consuming a vec as the original data
+    pub fn from_vec(values: Vec<u8>) -> PieceTable {
+        PieceTable::from_mapped(values)
+    }
+
+    /// Create a PieceTable over a read-only source, such as a memory mapped file, without
+    /// copying it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rex::util::piece_table::PieceTable;
+    ///
+    /// let mut pt = PieceTable::from_mapped(vec![1, 2, 3, 4]);
+    /// pt.splice(1..3, &[5]);
+    /// assert_eq!(pt.copy_out(0..pt.len()), [1, 5, 4]);
+    /// ```
+    pub fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> PieceTable {
+        let source_len = source.as_ref().len();
+        let pieces = if source_len > 0 {
+            vec![Piece { source: Source::Original, start: 0, len: source_len }]
+        } else {
+            vec![]
+        };
+
+        PieceTable {
+            original: Rc::new(source),
+            added: Vec::new(),
+            pieces: pieces,
+            length: source_len,
+        }
+    }
+
+    /// Return the length.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Return the number of pieces the data is currently made of.
+    pub fn num_pieces(&self) -> usize {
+        self.pieces.len()
+    }
+
+    fn piece_slice(&self, piece: &Piece) -> &[u8] {
+        let buf = match piece.source {
+            Source::Original => (*self.original).as_ref(),
+            Source::Added => &self.added[..],
+        };
+        &buf[piece.start..piece.start + piece.len]
+    }
+
+    /// Convert a global pos to the piece containing it and the offset inside that piece.
+    fn pos_to_piece(&self, pos: usize) -> (usize, usize) {
+        let mut cur_pos = pos;
+        for (i, piece) in self.pieces.iter().enumerate() {
+            if cur_pos < piece.len {
+                return (i, cur_pos);
+            }
+            cur_pos -= piece.len;
+        }
+
+        panic!("Position {} is out of bounds", pos);
+    }
+
+    /// Make sure a piece starts at the given position, splitting a piece if needed, and return
+    /// its index.
+    fn split_at(&mut self, pos: usize) -> usize {
+        if pos == self.length {
+            return self.pieces.len();
+        }
+
+        let (i, inner) = self.pos_to_piece(pos);
+        if inner == 0 {
+            return i;
+        }
+
+        let piece = self.pieces[i];
+        self.pieces[i].len = inner;
+        self.pieces.insert(i + 1, Piece {
+            source: piece.source,
+            start: piece.start + inner,
+            len: piece.len - inner,
+        });
+        i + 1
+    }
+
+    /// Give an iterator over a given range
+    pub fn iter_range<'a>(&'a self, range: Range<usize>) -> Items<'a> {
+        if range.end < range.start {
+            panic!("to ({}) is smaller than from ({})!", range.end, range.start);
+        }
+
+        let (piece, inner) = if range.start < self.length {
+            self.pos_to_piece(range.start)
+        } else {
+            (self.pieces.len(), 0)
+        };
+        Items {
+            table: self,
+            piece: piece,
+            inner: inner,
+            num_elem: range.end - range.start,
+        }
+    }
+
+    /// Provide an iterator over continuous memory slices
+    pub fn iter_slices<'a>(&'a self) -> Slices<'a> {
+        Slices {
+            table: self,
+            piece: 0,
+        }
+    }
+
+    /// Produce of copy of the supplied range
+    pub fn copy_out(&self, range: Range<usize>) -> Vec<u8> {
+        let mut res = Vec::with_capacity(range.end - range.start);
+        if range.start >= range.end {
+            return res;
+        }
+
+        let (first, mut inner) = self.pos_to_piece(range.start);
+        let mut remaining = range.end - range.start;
+        for piece in self.pieces[first..].iter() {
+            let slice = self.piece_slice(piece);
+            let take = cmp::min(remaining, slice.len() - inner);
+            res.extend_from_slice(&slice[inner..inner + take]);
+            remaining -= take;
+            inner = 0;
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        res
+    }
+
+    /// Replace values in range with the supplied values
+    ///
+    /// Only the piece list is modified, the inserted values are appended to the added buffer.
+    pub fn splice(&mut self, range: Range<usize>, values: &[u8]) -> Vec<u8> {
+        let added = self.add(values);
+        let replaced = self.splice_snapshot(range, &added);
+        self.copy_snapshot(&replaced)
+    }
+
+    /// Append values to the added buffer, returning them as a snapshot that can be spliced in.
+    pub fn add(&mut self, values: &[u8]) -> Snapshot {
+        let added_start = self.added.len();
+        self.added.extend_from_slice(values);
+
+        let pieces = if values.len() > 0 {
+            vec![Piece { source: Source::Added, start: added_start, len: values.len() }]
+        } else {
+            vec![]
+        };
+        Snapshot {
+            pieces: pieces,
+            length: values.len(),
+        }
+    }
+
+    /// Replace values in range with the pieces of a snapshot, returning a snapshot of the
+    /// replaced values.
+    ///
+    /// This costs O(pieces) no matter how much data is replaced, which makes it a cheap way to
+    /// undo edits.
+    pub fn splice_snapshot(&mut self, range: Range<usize>, snapshot: &Snapshot) -> Snapshot {
+        let from = range.start;
+        // Make sure that when we pull data out for the splice, we don't go over the end
+        let to = cmp::max(from, cmp::min(self.length, range.end));
+
+        let first = self.split_at(from);
+        let last = self.split_at(to);
+        let replaced = Snapshot {
+            pieces: self.pieces.drain(first..last).collect(),
+            length: to - from,
+        };
+        self.length -= to - from;
+
+        // Typing a run of bytes should not create a piece per byte, and undoing should not leave
+        // the pieces it restores split up, so merge pieces that continue the previous one.
+        let tail = self.pieces.split_off(first);
+        for &piece in snapshot.pieces.iter().chain(tail.iter()) {
+            if let Some(prev) = self.pieces.last_mut() {
+                if prev.source == piece.source && prev.start + prev.len == piece.start {
+                    prev.len += piece.len;
+                    continue;
+                }
+            }
+            self.pieces.push(piece);
+        }
+        self.length += snapshot.length;
+
+        replaced
+    }
+
+    /// Produce a copy of the values in a snapshot
+    pub fn copy_snapshot(&self, snapshot: &Snapshot) -> Vec<u8> {
+        let mut res = Vec::with_capacity(snapshot.length);
+        for piece in snapshot.pieces.iter() {
+            res.extend_from_slice(self.piece_slice(piece));
+        }
+        res
+    }
+}
+
+impl ops::Index<usize> for PieceTable {
+    type Output = u8;
+    fn index<'a>(&'a self, _index: usize) -> &'a u8 {
+        let (i, inner) = self.pos_to_piece(_index);
+        &self.piece_slice(&self.pieces[i])[inner]
+    }
+}
+
+impl fmt::Debug for PieceTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter_slices()).finish()
+    }
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = &'a u8;
+    fn next(&mut self) -> Option<&'a u8> {
+        if self.num_elem == 0 || self.piece >= self.table.pieces.len() {
+            return None;
+        }
+        self.num_elem -= 1;
+
+        let piece = &self.table.pieces[self.piece];
+        let elem = &self.table.piece_slice(piece)[self.inner];
+
+        self.inner += 1;
+        if self.inner >= piece.len {
+            self.inner = 0;
+            self.piece += 1;
+        }
+
+        Some(elem)
+    }
+}
+
+impl<'a> Iterator for Slices<'a> {
+    type Item = &'a [u8];
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.piece >= self.table.pieces.len() {
+            None
+        } else {
+            let i = self.piece;
+            self.piece += 1;
+            Some(self.table.piece_slice(&self.table.pieces[i]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::buffer::Buffer;
+
+    fn create_test_piece_table() -> (PieceTable, Vec<u8>) {
+        let v: Vec<u8> = (0..100).collect();
+        (PieceTable::from_vec(v.clone()), v)
+    }
+
+    #[test]
+    fn test_insert_delete_replace() {
+        let (mut pt, mut v) = create_test_piece_table();
+
+        pt.splice(10..10, &[0xAA, 0xBB]);
+        v.splice(10..10, vec![0xAA, 0xBB]);
+        pt.splice(50..60, &[]);
+        v.splice(50..60, vec![]);
+        pt.splice(5..15, &[0xCC]);
+        v.splice(5..15, vec![0xCC]);
+
+        assert_eq!(pt.len(), v.len());
+        assert_eq!(pt.copy_out(0..pt.len()), v);
+        assert_eq!(pt.iter_range(3..20).cloned().collect::<Vec<u8>>(), &v[3..20]);
+        assert_eq!(pt[6], v[6]);
+    }
+
+    #[test]
+    fn test_splice_returns_replaced() {
+        let (mut pt, _) = create_test_piece_table();
+
+        assert_eq!(pt.splice(98..200, &[1, 2, 3]), [98, 99]);
+        assert_eq!(pt.len(), 101);
+        assert_eq!(pt.copy_out(97..101), [97, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sequential_inserts_share_piece() {
+        let (mut pt, _) = create_test_piece_table();
+
+        for i in 0..16 {
+            pt.splice(20 + i..20 + i, &[0xFF]);
+        }
+
+        // The original split in two around a single added piece
+        assert_eq!(pt.num_pieces(), 3);
+        assert_eq!(pt.copy_out(20..36), vec![0xFF; 16]);
+    }
+
+    #[test]
+    fn test_splice_snapshot() {
+        let (mut pt, v) = create_test_piece_table();
+
+        let added = pt.add(&[1, 2, 3]);
+        let replaced = pt.splice_snapshot(10..60, &added);
+        assert_eq!(replaced.len(), 50);
+        assert_eq!(pt.copy_out(9..14), [9, 1, 2, 3, 60]);
+
+        // Putting the replaced pieces back restores the original piece
+        pt.splice_snapshot(10..13, &replaced);
+        assert_eq!(pt.copy_out(0..pt.len()), v);
+        assert_eq!(pt.num_pieces(), 1);
+    }
+
+    #[test]
+    fn test_find() {
+        let (mut pt, _) = create_test_piece_table();
+
+        pt.splice(40..40, &[0xDE, 0xAD]);
+
+        assert_eq!(Some(39), pt.find_slice_from(0, &[39, 0xDE, 0xAD, 40]));
+        assert_eq!(None, pt.find_slice_from(40, &[39, 0xDE]));
+    }
+}
@@ -0,0 +1,607 @@
+//! A small regular expression engine for searching binary data.
+//!
+//! Patterns match bytes rather than characters: `.` matches any byte, `\xHH` matches a byte by
+//! its value and classes such as `[\x20-\x7e]` are sets of bytes. Alternation, groups and the
+//! `*`, `+`, `?` and `{n,m}` repetitions are supported, anchors and captures aren't.
+//!
+//! The pattern is compiled to a program that is run over the data one byte at a time, following
+//! all the ways it can match at once (a Pike VM), so the data can come in any number of slices and
+//! is only read once.
+
+use std::cmp;
+use std::fmt;
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+/// The most instructions a compiled pattern may have, big counted repetitions can get there
+const MAX_PROGRAM_LEN: usize = 20000;
+
+/// A set of byte values
+#[derive(Clone, PartialEq, Eq)]
+struct ByteSet([u32; 8]);
+
+impl ByteSet {
+    fn empty() -> ByteSet {
+        ByteSet([0; 8])
+    }
+
+    fn all() -> ByteSet {
+        ByteSet([!0; 8])
+    }
+
+    fn single(b: u8) -> ByteSet {
+        let mut set = ByteSet::empty();
+        set.insert(b);
+        set
+    }
+
+    fn insert(&mut self, b: u8) {
+        self.0[b as usize / 32] |= 1 << (b % 32);
+    }
+
+    fn insert_range(&mut self, from: u8, to: u8) {
+        for b in from as u16..to as u16 + 1 {
+            self.insert(b as u8);
+        }
+    }
+
+    fn union(&mut self, other: &ByteSet) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a |= *b;
+        }
+    }
+
+    fn negate(&mut self) {
+        for a in self.0.iter_mut() {
+            *a = !*a;
+        }
+    }
+
+    fn contains(&self, b: u8) -> bool {
+        self.0[b as usize / 32] & (1 << (b % 32)) != 0
+    }
+}
+
+impl fmt::Debug for ByteSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries((0..256).filter(|&b| self.contains(b as u8))).finish()
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Set(ByteSet),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Box<Node>, u32, Option<u32>),
+}
+
+impl Node {
+    /// The length of the longest data the node can match, or None if there is no limit
+    fn max_len(&self) -> Option<usize> {
+        match *self {
+            Node::Set(_) => Some(1),
+            Node::Concat(ref nodes) => nodes.iter().fold(Some(0), |len, node|
+                len.and_then(|len| node.max_len().map(|node_len| len + node_len))
+            ),
+            Node::Alternate(ref nodes) => nodes.iter().fold(Some(0), |len, node|
+                len.and_then(|len| node.max_len().map(|node_len| cmp::max(len, node_len)))
+            ),
+            Node::Repeat(ref node, _, max) => match (node.max_len(), max) {
+                (Some(0), _) => Some(0),
+                (Some(len), Some(max)) => Some(len * max as usize),
+                _ => None,
+            },
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_alternate(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![try!(self.parse_concat())];
+        while self.chars.peek() == Some(&'|') {
+            self.chars.next();
+            alternatives.push(try!(self.parse_concat()));
+        }
+        Ok(if alternatives.len() == 1 { alternatives.pop().unwrap() } else { Node::Alternate(alternatives) })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![];
+        loop {
+            match self.chars.peek() {
+                None | Some(&'|') | Some(&')') => break,
+                _ => nodes.push(try!(self.parse_repeat())),
+            }
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_repeat(&mut self) -> Result<Node, String> {
+        let mut node = try!(self.parse_atom());
+        loop {
+            let (min, max) = match self.chars.peek().cloned() {
+                Some('*') => { self.chars.next(); (0, None) }
+                Some('+') => { self.chars.next(); (1, None) }
+                Some('?') => { self.chars.next(); (0, Some(1)) }
+                Some('{') => {
+                    self.chars.next();
+                    try!(self.parse_counts())
+                }
+                _ => break,
+            };
+            // A lazy repetition finds the same leftmost start as a greedy one
+            if self.chars.peek() == Some(&'?') {
+                self.chars.next();
+            }
+            node = Node::Repeat(Box::new(node), min, max);
+        }
+        Ok(node)
+    }
+
+    /// Parse the counts of a `{n}`, `{n,}` or `{n,m}` repetition, after the opening brace.
+    fn parse_counts(&mut self) -> Result<(u32, Option<u32>), String> {
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                Some('}') => break,
+                Some(c) => text.push(c),
+                None => return Err("Unclosed repetition".to_string()),
+            }
+        }
+
+        let parse = |s: &str| s.trim().parse::<u32>().map_err(|_| format!("Invalid repetition {{{}}}", text));
+        let (min, max) = match text.find(',') {
+            None => { let n = try!(parse(&text)); (n, Some(n)) }
+            Some(i) if text[i + 1..].trim().is_empty() => (try!(parse(&text[..i])), None),
+            Some(i) => (try!(parse(&text[..i])), Some(try!(parse(&text[i + 1..])))),
+        };
+        if max.map_or(false, |max| max < min) {
+            return Err(format!("Invalid repetition {{{}}}", text));
+        }
+        Ok((min, max))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.chars.next() {
+            Some('(') => {
+                // Groups don't capture, so the non-capturing syntax means the same
+                if self.chars.peek() == Some(&'?') {
+                    self.chars.next();
+                    if self.chars.next() != Some(':') {
+                        return Err("Unknown group type".to_string());
+                    }
+                }
+                let node = try!(self.parse_alternate());
+                if self.chars.next() != Some(')') {
+                    return Err("Unclosed group".to_string());
+                }
+                Ok(node)
+            }
+            Some(')') => Err("Unmatched )".to_string()),
+            Some('[') => self.parse_class().map(Node::Set),
+            Some('.') => Ok(Node::Set(ByteSet::all())),
+            Some('\\') => self.parse_escape().map(Node::Set),
+            Some('*') | Some('+') | Some('?') | Some('{') => Err("Nothing to repeat".to_string()),
+            Some('^') | Some('$') => Err("Anchors aren't supported".to_string()),
+            Some(c) => Parser::byte_of(c).map(|b| Node::Set(ByteSet::single(b))),
+            None => Err("Unexpected end of pattern".to_string()),
+        }
+    }
+
+    fn byte_of(c: char) -> Result<u8, String> {
+        if (c as u32) < 0x80 {
+            Ok(c as u8)
+        } else {
+            Err(format!("Use \\x escapes for {}", c))
+        }
+    }
+
+    /// Parse an escape after its backslash, as the set of bytes it matches.
+    fn parse_escape(&mut self) -> Result<ByteSet, String> {
+        let c = match self.chars.next() {
+            Some(c) => c,
+            None => return Err("Unexpected end of pattern".to_string()),
+        };
+        let mut set = ByteSet::empty();
+        match c {
+            'x' => {
+                let hex: String = self.chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(b) if hex.len() == 2 && hex.chars().all(|c| c.is_digit(16)) => set.insert(b),
+                    _ => return Err("\\x needs two hex digits".to_string()),
+                }
+            }
+            'n' => set.insert(b'\n'),
+            'r' => set.insert(b'\r'),
+            't' => set.insert(b'\t'),
+            '0' => set.insert(0),
+            'd' | 'D' => set.insert_range(b'0', b'9'),
+            'w' | 'W' => {
+                set.insert_range(b'a', b'z');
+                set.insert_range(b'A', b'Z');
+                set.insert_range(b'0', b'9');
+                set.insert(b'_');
+            }
+            's' | 'S' => {
+                for &b in b" \t\n\r\x0b\x0c" {
+                    set.insert(b);
+                }
+            }
+            c if c.is_alphanumeric() => return Err(format!("Unknown escape \\{}", c)),
+            c => set.insert(try!(Parser::byte_of(c))),
+        }
+        if c == 'D' || c == 'W' || c == 'S' {
+            set.negate();
+        }
+        Ok(set)
+    }
+
+    /// Parse a class after its opening bracket.
+    fn parse_class(&mut self) -> Result<ByteSet, String> {
+        let mut set = ByteSet::empty();
+        let negated = self.chars.peek() == Some(&'^');
+        if negated {
+            self.chars.next();
+        }
+
+        let mut first = true;
+        loop {
+            let item = match self.chars.next() {
+                Some(']') if !first => break,
+                Some('\\') => try!(self.parse_escape()),
+                Some(c) => ByteSet::single(try!(Parser::byte_of(c))),
+                None => return Err("Unclosed class".to_string()),
+            };
+            first = false;
+
+            // A range, unless the dash is last in the class
+            let mut lookahead = self.chars.clone();
+            if lookahead.next() == Some('-') && lookahead.peek().map_or(false, |&c| c != ']') {
+                self.chars.next();
+                let to = match self.chars.next() {
+                    Some('\\') => try!(self.parse_escape()),
+                    Some(c) => ByteSet::single(try!(Parser::byte_of(c))),
+                    None => return Err("Unclosed class".to_string()),
+                };
+                match (single_byte(&item), single_byte(&to)) {
+                    (Some(from), Some(to)) if from <= to => set.insert_range(from, to),
+                    _ => return Err("Invalid class range".to_string()),
+                }
+            } else {
+                set.union(&item);
+            }
+        }
+
+        if negated {
+            set.negate();
+        }
+        Ok(set)
+    }
+}
+
+/// The byte of a set with a single byte in it
+fn single_byte(set: &ByteSet) -> Option<u8> {
+    let mut bytes = (0..256).filter(|&b| set.contains(b as u8));
+    match (bytes.next(), bytes.next()) {
+        (Some(b), None) => Some(b as u8),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    /// Consume a byte in the set
+    Byte(ByteSet),
+    /// Continue at both, preferring the first
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// A compiled pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regex {
+    program: Vec<Inst>,
+    max_len: Option<usize>,
+}
+
+impl Regex {
+    /// Compile a pattern, returning a description of the problem if it is invalid.
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.chars().peekable() };
+        let node = try!(parser.parse_alternate());
+        if parser.chars.next().is_some() {
+            return Err("Unmatched )".to_string());
+        }
+
+        let mut program = vec![];
+        try!(Regex::compile(&node, &mut program));
+        program.push(Inst::Match);
+        Ok(Regex {
+            program: program,
+            max_len: node.max_len(),
+        })
+    }
+
+    /// The length of the longest match, or None if matches can be as long as the data.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    fn compile(node: &Node, program: &mut Vec<Inst>) -> Result<(), String> {
+        if program.len() > MAX_PROGRAM_LEN {
+            return Err("Pattern is too big".to_string());
+        }
+        match *node {
+            Node::Set(ref set) => program.push(Inst::Byte(set.clone())),
+            Node::Concat(ref nodes) => {
+                for node in nodes {
+                    try!(Regex::compile(node, program));
+                }
+            }
+            Node::Alternate(ref nodes) => {
+                let mut jumps = vec![];
+                for (i, node) in nodes.iter().enumerate() {
+                    let split = program.len();
+                    if i + 1 < nodes.len() {
+                        program.push(Inst::Split(split + 1, 0));
+                    }
+                    try!(Regex::compile(node, program));
+                    if i + 1 < nodes.len() {
+                        jumps.push(program.len());
+                        program.push(Inst::Jump(0));
+                        let next = program.len();
+                        program[split] = Inst::Split(split + 1, next);
+                    }
+                }
+                let end = program.len();
+                for jump in jumps {
+                    program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat(ref node, min, max) => {
+                for _ in 0..min {
+                    try!(Regex::compile(node, program));
+                }
+                match max {
+                    None => {
+                        let split = program.len();
+                        program.push(Inst::Split(split + 1, 0));
+                        try!(Regex::compile(node, program));
+                        program.push(Inst::Jump(split));
+                        let end = program.len();
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                    Some(max) => {
+                        let mut splits = vec![];
+                        for _ in min..max {
+                            splits.push(program.len());
+                            program.push(Inst::Split(0, 0));
+                            try!(Regex::compile(node, program));
+                        }
+                        let end = program.len();
+                        for split in splits {
+                            program[split] = Inst::Split(split + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a thread at pc to the list, following jumps and splits. Each instruction gets a single
+    /// thread, the first one to reach it, which is the one that started earliest.
+    fn add_thread(&self, list: &mut Vec<(usize, usize)>, seen: &mut [bool], pc: usize, start: usize) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if seen[pc] {
+                continue;
+            }
+            seen[pc] = true;
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                _ => list.push((pc, start)),
+            }
+        }
+    }
+
+    /// Find the start of the leftmost match in data given as consecutive slices, as an offset
+    /// from the start of the data.
+    pub fn find_in_slices<'a, I: Iterator<Item=&'a [u8]>>(&self, slices: I) -> Option<usize> {
+        let mut current: Vec<(usize, usize)> = vec![];
+        let mut next = vec![];
+        let mut seen = vec![false; self.program.len()];
+        let mut found: Option<usize> = None;
+        let mut pos = 0;
+
+        let mut bytes = slices.flat_map(|slice| slice.iter().cloned());
+        loop {
+            // Threads are kept in the order they started, so a new one goes last
+            if found.is_none() {
+                self.add_thread(&mut current, &mut seen, 0, pos);
+            }
+            if current.is_empty() {
+                break;
+            }
+
+            let b = bytes.next();
+            for s in seen.iter_mut() {
+                *s = false;
+            }
+            for &(pc, start) in current.iter() {
+                match self.program[pc] {
+                    Inst::Match => {
+                        // Threads after this one started later, so they can't find an earlier match
+                        found = Some(start);
+                        break;
+                    }
+                    Inst::Byte(ref set) => match b {
+                        Some(b) if set.contains(b) => self.add_thread(&mut next, &mut seen, pc + 1, start),
+                        _ => (),
+                    },
+                    _ => unreachable!(),
+                }
+            }
+
+            if b.is_none() {
+                break;
+            }
+            if let Some(found) = found {
+                next.retain(|&(_, start)| start < found);
+            }
+            current.clear();
+            mem::swap(&mut current, &mut next);
+            pos += 1;
+        }
+        found
+    }
+
+    /// Find the start of the rightmost match that starts before limit, in data given as
+    /// consecutive slices, as an offset from the start of the data. The data is read once.
+    pub fn rfind_in_slices<'a, I: Iterator<Item=&'a [u8]>>(&self, slices: I, limit: usize) -> Option<usize> {
+        let mut current: Vec<(usize, usize)> = vec![];
+        let mut next = vec![];
+        let mut seen = vec![false; self.program.len()];
+        let mut found: Option<usize> = None;
+        let mut pos = 0;
+
+        let mut bytes = slices.flat_map(|slice| slice.iter().cloned());
+        loop {
+            // Threads are kept with the latest start first, so a new one goes first and takes
+            // the instructions it reaches from the threads that started earlier
+            for s in seen.iter_mut() {
+                *s = false;
+            }
+            if pos < limit {
+                self.add_thread(&mut next, &mut seen, 0, pos);
+            }
+            for &(pc, start) in current.iter() {
+                if !seen[pc] {
+                    seen[pc] = true;
+                    next.push((pc, start));
+                }
+            }
+            current.clear();
+            mem::swap(&mut current, &mut next);
+            if current.is_empty() && pos >= limit {
+                break;
+            }
+
+            let b = bytes.next();
+            for s in seen.iter_mut() {
+                *s = false;
+            }
+            for &(pc, start) in current.iter() {
+                match self.program[pc] {
+                    Inst::Match => {
+                        // Threads after this one started earlier, so they can't find a later match
+                        if found.map_or(true, |found| start > found) {
+                            found = Some(start);
+                        }
+                        break;
+                    }
+                    Inst::Byte(ref set) => match b {
+                        Some(b) if set.contains(b) => self.add_thread(&mut next, &mut seen, pc + 1, start),
+                        _ => (),
+                    },
+                    _ => unreachable!(),
+                }
+            }
+
+            if b.is_none() {
+                break;
+            }
+            if let Some(found) = found {
+                next.retain(|&(_, start)| start > found);
+            }
+            current.clear();
+            mem::swap(&mut current, &mut next);
+            pos += 1;
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find(pattern: &str, data: &[u8]) -> Option<usize> {
+        Regex::new(pattern).unwrap().find_in_slices(data.chunks(3))
+    }
+
+    #[test]
+    fn test_literals_and_classes() {
+        assert_eq!(find("abc", b"xxabcx"), Some(2));
+        assert_eq!(find("a.c", b"a\nc"), Some(0));
+        assert_eq!(find("\\x7fELF", b"..\x7fELF"), Some(2));
+        assert_eq!(find("[\\x20-\\x7e]{4,}", b"\x00ab\x01abcd"), Some(4));
+        assert_eq!(find("[^a-z]", b"abc1"), Some(3));
+        assert_eq!(find("[a-]", b"x-"), Some(1));
+        assert_eq!(find("\\d\\d", b"a1b23"), Some(3));
+        assert_eq!(find("abd", b"abcabc"), None);
+    }
+
+    #[test]
+    fn test_repetition_and_alternation() {
+        assert_eq!(find("\\x7fELF.{12}\\x02\\x00", b"\x00\x7fELF0123456789ab\x02\x00"), Some(1));
+        assert_eq!(find("\\x7fELF.{12}\\x02\\x00", b"\x7fELF0123456789a\x02\x00"), None);
+        assert_eq!(find("ab+c", b"acabbbc"), Some(2));
+        assert_eq!(find("x(ab|cd)*y", b"xy xababcdy"), Some(0));
+        assert_eq!(find("cat|dog", b"hotdog cat"), Some(3));
+        assert_eq!(find("a?b{2,3}", b"abbb"), Some(0));
+        assert_eq!(find("", b"abc"), Some(0));
+        // The leftmost match wins, even if another one is found first
+        assert_eq!(find("a.*z|bc", b"abcz"), Some(0));
+    }
+
+    #[test]
+    fn test_rfind() {
+        let rfind = |pattern: &str, data: &[u8], limit: usize|
+            Regex::new(pattern).unwrap().rfind_in_slices(data.chunks(3), limit);
+        assert_eq!(rfind("ab", b"abxabxab", 8), Some(6));
+        assert_eq!(rfind("ab", b"abxabxab", 6), Some(3));
+        assert_eq!(rfind("ab", b"abxabxab", 0), None);
+        // A match can go past the limit, as long as it starts before it
+        assert_eq!(rfind("x.*z", b"x.x..z", 3), Some(2));
+        assert_eq!(rfind("[\\x20-\\x7e]{4,}", b"abcdefgh\x00abcd", 9), Some(4));
+        // Patterns that match nothing match right before the limit
+        assert_eq!(rfind("a*", b"bbbb", 3), Some(2));
+        assert_eq!(rfind("x?", b"xxb", 3), Some(2));
+        assert_eq!(rfind("bc|abcd", b"abcd", 4), Some(1));
+    }
+
+    #[test]
+    fn test_max_len() {
+        let max_len = |pattern: &str| Regex::new(pattern).unwrap().max_len();
+        assert_eq!(max_len("\\x7fELF.{12}"), Some(16));
+        assert_eq!(max_len("ab|c{2,5}"), Some(5));
+        assert_eq!(max_len("a?b"), Some(2));
+        assert_eq!(max_len(""), Some(0));
+        assert_eq!(max_len("ab+"), None);
+        assert_eq!(max_len("x(ab|cd)*y"), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Regex::new("(ab").is_err());
+        assert!(Regex::new("ab)").is_err());
+        assert!(Regex::new("[ab").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("\\xg0").is_err());
+        assert!(Regex::new("a{3,1}").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::new("a{10000}{10000}").is_err());
+    }
+}
@@ -4,10 +4,12 @@ use std::fmt;
 use std::ops;
 use std::ops::{Range, RangeFrom, RangeTo, RangeFull};
 use std::cmp;
+use std::rc::Rc;
 
-use itertools;
 use odds::vec::VecExt;
 
+use super::buffer;
+
 /// A generic trait over Rust's built types.
 pub trait FromRange {
     #[inline(always)]
@@ -50,9 +52,22 @@ impl FromRange for RangeTo<usize> {
 /// has a m inimum and maximum block size. As data is inserted and deleted, from any location,
 /// SplitVec will split up and merge the blocks to try and stay between those minimum and maximum
 /// block sizes.
+///
+/// A SplitVec can also be created over a read-only source, such as a memory mapped file (see
+/// [from_mapped](#method.from_mapped)). In that case the blocks start out as ranges into the
+/// source, and a block is only copied into memory once it is edited.
 pub struct SplitVec {
-    vecs: Vec<Vec<u8>>,
+    vecs: Vec<Block>,
     length: usize,
+    source: Option<Rc<AsRef<[u8]>>>,
+}
+
+/// A single block of a SplitVec.
+enum Block {
+    /// Data that is owned by the SplitVec.
+    Owned(Vec<u8>),
+    /// An untouched range in the read-only source of the SplitVec.
+    Mapped(Range<usize>),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -90,22 +105,20 @@ impl SplitVec {
         SplitVec {
             vecs: Vec::new(),
             length: 0,
+            source: None,
         }
     }
 
     /// Create a SplitVec by consuming a vec as the initial data vector
     pub fn from_vec(values: Vec<u8>) -> SplitVec {
-        let len = values.len();
-        SplitVec {
-            vecs: vec![values],
-            length: len,
-        }
+        SplitVec::from_vecs(vec![values])
     }
 
     pub fn from_vecs(vecs: Vec<Vec<u8>>) -> SplitVec {
         let mut sv = SplitVec {
-            vecs: vecs,
+            vecs: vecs.into_iter().filter(|v| v.len() != 0).map(Block::Owned).collect(),
             length: 0,
+            source: None,
         };
         sv.calc_len();
         sv
@@ -113,9 +126,38 @@ impl SplitVec {
 
     /// Create a SplitVec by copying in values from a slice
     pub fn from_slice(values: &[u8]) -> SplitVec {
+        SplitVec::from_vec(values.into())
+    }
+
+    /// Create a SplitVec over a read-only source without copying it.
+    ///
+    /// The source is usually a memory mapped file. Only the blocks that are modified are copied
+    /// into memory, so this allows for editing files that are much larger than the available
+    /// memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rex::util::split_vec::SplitVec;
+    ///
+    /// let mut sv = SplitVec::from_mapped(vec![1, 2, 3, 4]);
+    /// sv.splice(1..3, &[5]);
+    /// assert_eq!(sv.copy_out(..), [1, 5, 4]);
+    /// ```
+    pub fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> SplitVec {
+        let source_len = source.as_ref().len();
+        let mut vecs = Vec::new();
+        let mut start = 0;
+        while start < source_len {
+            let end = cmp::min(start + MAX_BLOCK_SIZE, source_len);
+            vecs.push(Block::Mapped(start..end));
+            start = end;
+        }
+
         SplitVec {
-            vecs: vec![values.into()],
-            length: values.len(),
+            vecs: vecs,
+            length: source_len,
+            source: Some(Rc::new(source)),
         }
     }
 
@@ -126,12 +168,61 @@ impl SplitVec {
 
     /// Update the saved length value so that the len func will be -O(1)
     fn calc_len(&mut self) {
-        self.length = 0;
-        for len in self.vecs.iter().map(|v| v.len()) {
-            self.length += len
+        let mut length = 0;
+        for i in 0..self.vecs.len() {
+            length += self.block_len(i);
+        }
+        self.length = length;
+    }
+
+    /// Return the contents of a block, wherever they are stored.
+    fn block(&self, outer: usize) -> &[u8] {
+        match self.vecs[outer] {
+            Block::Owned(ref vec) => vec,
+            Block::Mapped(ref range) => {
+                let source = self.source.as_ref().expect("Mapped block without a source");
+                &(**source).as_ref()[range.clone()]
+            }
+        }
+    }
+
+    fn block_len(&self, outer: usize) -> usize {
+        match self.vecs[outer] {
+            Block::Owned(ref vec) => vec.len(),
+            Block::Mapped(ref range) => range.len(),
         }
     }
 
+    /// Return a block for modification, copying it out of the source if needed.
+    fn block_mut(&mut self, outer: usize) -> &mut Vec<u8> {
+        let materialized = match self.vecs[outer] {
+            Block::Owned(_) => None,
+            Block::Mapped(_) => Some(self.block(outer).to_vec()),
+        };
+        if let Some(vec) = materialized {
+            self.vecs[outer] = Block::Owned(vec);
+        }
+
+        match self.vecs[outer] {
+            Block::Owned(ref mut vec) => vec,
+            Block::Mapped(_) => unreachable!(),
+        }
+    }
+
+    /// Split a block in two at the given inner offset. Mapped blocks stay mapped.
+    fn split_block(&mut self, outer: usize, at: usize) {
+        let new_block = match self.vecs[outer] {
+            Block::Owned(ref mut vec) => Block::Owned(vec.split_off(at)),
+            Block::Mapped(ref mut range) => {
+                let split_at = range.start + at;
+                let new_range = split_at..range.end;
+                range.end = split_at;
+                Block::Mapped(new_range)
+            }
+        };
+        self.vecs.insert(outer + 1, new_block);
+    }
+
     /// Convert a global pos to a locall index
     fn pos_to_index(&self, pos: usize, for_insert: bool) -> Index {
         if pos == 0 {
@@ -139,14 +230,15 @@ impl SplitVec {
         }
 
         let mut cur_pos = pos;
-        for (i, vec) in self.vecs.iter().enumerate() {
-            if cur_pos < vec.len() || (for_insert && cur_pos == vec.len()) {
+        for i in 0..self.vecs.len() {
+            let block_len = self.block_len(i);
+            if cur_pos < block_len || (for_insert && cur_pos == block_len) {
                 return Index {
                     outer: i,
                     inner: cur_pos,
                 }
             }
-            cur_pos -= vec.len();
+            cur_pos -= block_len;
         }
 
         panic!("Position {} is out of bounds", pos);
@@ -180,26 +272,22 @@ impl SplitVec {
     /// Prepare an index for future text insertion, splitting/merging big/small sections respectively
     fn prepare_insert(&mut self, index: Index) -> Index {
         if index.outer >= self.vecs.len() {
-            self.vecs.push(Vec::new());
+            self.vecs.push(Block::Owned(Vec::new()));
         }
 
-        if self.vecs[index.outer].len() < MAX_BLOCK_SIZE {
+        if self.block_len(index.outer) < MAX_BLOCK_SIZE {
             return index;
         }
 
         let page_start_idx = (index.inner / MIN_BLOCK_SIZE) * MIN_BLOCK_SIZE;
         if page_start_idx == 0 {
-            if self.vecs[index.outer].len() > MAX_BLOCK_SIZE {
-                let insert_vec: Vec < _ >= self.vecs[index.outer][MIN_BLOCK_SIZE..].into();
-                self.vecs.insert(index.outer + 1, insert_vec);
-                self.vecs[index.outer].truncate(MIN_BLOCK_SIZE);
+            if self.block_len(index.outer) > MAX_BLOCK_SIZE {
+                self.split_block(index.outer, MIN_BLOCK_SIZE);
             }
 
             return index;
         } else {
-            let insert_vec: Vec<_> = self.vecs[index.outer][page_start_idx..].into();
-            self.vecs.insert(index.outer + 1, insert_vec);
-            self.vecs[index.outer].truncate(page_start_idx);
+            self.split_block(index.outer, page_start_idx);
             return self.prepare_insert(Index {
                 outer: index.outer + 1,
                 inner: index.inner - page_start_idx
@@ -209,17 +297,43 @@ impl SplitVec {
 
     /// insert all values from a slice at an offset.
     fn insert(&mut self, offset: usize, values: &[u8]) {
+        if values.len() == 0 {
+            return;
+        }
+
         let mut index = self.pos_to_index(offset, true);
         index = self.prepare_insert(index);
 
         // This is needed for the mut borrow vec
         {
-            self.vecs[index.outer].splice(index.inner..index.inner, values.into_iter().cloned());
+            self.block_mut(index.outer).splice(index.inner..index.inner, values.into_iter().cloned());
         }
 
         self.calc_len();
     }
 
+    /// Take out a range from a single block. Removing a prefix or suffix of a mapped block only
+    /// shrinks the mapped range, anything else copies the block into memory first.
+    fn drain_block(&mut self, outer: usize, begin: usize, end: usize) -> Vec<u8> {
+        let block_len = self.block_len(outer);
+        let res = self.block(outer)[begin..end].to_vec();
+
+        match self.vecs[outer] {
+            Block::Mapped(ref mut range) if begin == 0 || end == block_len => {
+                if begin == 0 {
+                    range.start += end;
+                } else {
+                    range.end = range.start + begin;
+                }
+                return res;
+            }
+            _ => (),
+        }
+
+        self.block_mut(outer).drain(begin..end);
+        res
+    }
+
     /// Moves data out from the supplied range.
     fn move_out<R: FromRange>(&mut self, range: R) -> Vec<u8> {
         let (from, to) = range.from_range(self);
@@ -229,13 +343,7 @@ impl SplitVec {
         let begin = self.pos_to_index(from, false);
         let end = self.pos_to_index(to, true);
 
-        // Simple case, by staying on the same section
-        if begin.outer == end.outer {
-            return self.vecs[begin.outer].drain(begin.inner..end.inner).collect();
-        }
-
-        // First drain out the result
-        for outer_index in begin.outer..end.outer {
+        for outer_index in begin.outer..(end.outer + 1) {
             // Drain what we can
             let drain_begin = if outer_index != begin.outer {
                 0
@@ -243,16 +351,19 @@ impl SplitVec {
                 begin.inner
             };
             let drain_end = if outer_index != end.outer {
-                self.vecs[outer_index].len()
+                self.block_len(outer_index)
             } else {
                 end.inner
             };
 
-            res.extend(self.vecs[outer_index].drain(drain_begin..drain_end));
+            res.extend(self.drain_block(outer_index, drain_begin, drain_end));
         }
 
         // TODO: Make this also possible to merge small vecs
-        self.vecs.retain(|vec| vec.len() != 0);
+        self.vecs.retain(|block| match *block {
+            Block::Owned(ref vec) => vec.len() != 0,
+            Block::Mapped(ref range) => range.len() != 0,
+        });
 
         self.calc_len();
 
@@ -260,11 +371,23 @@ impl SplitVec {
     }
 
     /// Produce of copy of the supplied range
-    pub fn copy_out<R: FromRange>(&mut self, range: R) -> Vec<u8> {
+    pub fn copy_out<R: FromRange>(&self, range: R) -> Vec<u8> {
         let (from, to) = range.from_range(self);
+        let mut res = Vec::with_capacity(to - from);
+        if from == to {
+            return res;
+        }
 
-        // TODO: Make this use direct cocpy rather than iterators
-        self.iter_range(from..to).map(|x| *x).collect::<Vec<u8>>()
+        let begin = self.pos_to_index(from, false);
+        let end = self.pos_to_index(to, true);
+        for outer_index in begin.outer..(end.outer + 1) {
+            let block = self.block(outer_index);
+            let copy_begin = if outer_index != begin.outer { 0 } else { begin.inner };
+            let copy_end = if outer_index != end.outer { block.len() } else { end.inner };
+            res.extend_from_slice(&block[copy_begin..copy_end]);
+        }
+
+        res
     }
 
     /// Replace values in range with the supplied values
@@ -294,17 +417,30 @@ impl SplitVec {
 
     /// Find a slice from a certain index and onward
     pub fn find_slice_from(&self, from: usize, needle: &[u8]) -> Option<usize> {
-        for i in from..self.len() {
-            if itertools::equal(self.iter_range(i..i+needle.len()), needle.iter()) {
-                return Some(i);
-            }
+        if from >= self.len() {
+            return None;
         }
-        None
+        buffer::find_in_iter(self.iter_range(from..), needle).map(|pos| from + pos)
+    }
+
+    /// Find bytes that match needle in the bits set in mask, such as a signature with wildcards,
+    /// from a certain index and onward. Matches can span blocks.
+    pub fn find_masked_from(&self, from: usize, needle: &[u8], mask: &[u8]) -> Option<usize> {
+        if from >= self.len() {
+            return None;
+        }
+        buffer::find_masked_in_iter(self.iter_range(from..), needle, mask).map(|pos| from + pos)
+    }
+
+    /// Find the last occurrence of a slice that starts before a certain index. Matches can span
+    /// blocks.
+    pub fn rfind_slice_from(&self, from: usize, needle: &[u8]) -> Option<usize> {
+        <SplitVec as buffer::Buffer>::rfind_slice_from(self, from, needle)
     }
 
     #[cfg(test)]
     fn get_lengths(&self) -> Vec<usize> {
-        self.vecs.iter().map(|v| v.len()).collect::<Vec<usize>>()
+        (0..self.vecs.len()).map(|i| self.block_len(i)).collect::<Vec<usize>>()
     }
 }
 
@@ -312,20 +448,20 @@ impl ops::Index<usize> for SplitVec {
     type Output = u8;
     fn index<'a>(&'a self, _index: usize) -> &'a u8 {
         let idx = self.pos_to_index(_index, false);
-        &self.vecs[idx.outer][idx.inner]
+        &self.block(idx.outer)[idx.inner]
     }
 }
 
 impl ops::IndexMut<usize> for SplitVec {
     fn index_mut<'a>(&'a mut self, _index: usize) -> &'a mut u8 {
         let idx = self.pos_to_index(_index, false);
-        &mut self.vecs[idx.outer][idx.inner]
+        &mut self.block_mut(idx.outer)[idx.inner]
     }
 }
 
 impl fmt::Debug for SplitVec {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.vecs.fmt(f)
+        f.debug_list().entries(self.iter_slices()).finish()
     }
 }
 
@@ -343,12 +479,12 @@ impl<'a> Iterator for Items<'a> {
         }
 
         let elem = {
-            let vv = &self.seg.vecs[self.index.outer];
+            let vv = self.seg.block(self.index.outer);
             &vv[self.index.inner]
         };
 
         self.index.inner += 1;
-        if self.index.inner >= self.seg.vecs[self.index.outer].len() {
+        if self.index.inner >= self.seg.block_len(self.index.outer) {
             self.index.inner = 0;
             self.index.outer += 1;
         }
@@ -371,12 +507,12 @@ impl<'a> Iterator for MutItems<'a> {
         }
 
         let elem_raw: *mut u8 = {
-            let vv = &mut self.seg.vecs[self.index.outer];
+            let vv = self.seg.block_mut(self.index.outer);
             &mut vv[self.index.inner]
         };
 
         self.index.inner += 1;
-        if self.index.inner >= self.seg.vecs[self.index.outer].len() {
+        if self.index.inner >= self.seg.block_len(self.index.outer) {
             self.index.inner = 0;
             self.index.outer += 1;
         }
@@ -393,7 +529,7 @@ impl<'a> Iterator for Slices<'a> {
         } else {
             let i = self.outer;
             self.outer += 1;
-            Some(&self.seg.vecs[i])
+            Some(self.seg.block(i))
         }
     }
 }
@@ -440,6 +576,7 @@ mod test {
 
         assert_eq!(sv[SIZE-11], 0);
         assert_eq!(sv[SIZE-10], 1);
+        assert_eq!(sv.len(), 2*SIZE - 20);
     }
 
     const MIDDLE: usize = SIZE/2;
@@ -512,4 +649,82 @@ mod test {
         seg[index+1] = sentinal +1;
         assert_eq!(Some(index), seg.find_slice(&[sentinal, sentinal+1]));
     }
+
+    fn create_test_mapped() -> (SplitVec, Vec<u8>) {
+        let v: Vec<u8> = (0..3*MAX_BLOCK_SIZE).map(|x| (x & 0xff) as u8).collect();
+        (SplitVec::from_mapped(v.clone()), v)
+    }
+
+    #[test]
+    fn test_mapped_is_split_to_blocks() {
+        let (sv, v) = create_test_mapped();
+
+        assert_eq!(sv.get_lengths(), vec![MAX_BLOCK_SIZE; 3]);
+        assert_eq!(sv.len(), v.len());
+        assert_eq!(sv[MAX_BLOCK_SIZE + 5], v[MAX_BLOCK_SIZE + 5]);
+        assert!(sv.iter_slices().zip(v.chunks(MAX_BLOCK_SIZE)).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_mapped_splice() {
+        let (mut sv, mut v) = create_test_mapped();
+
+        // Across a block boundary
+        let start = MAX_BLOCK_SIZE - 10;
+        sv.splice(start..start + 20, &[0xAA, 0xBB]);
+        v.splice(start..start + 20, vec![0xAA, 0xBB]);
+        // Inside a single block
+        sv.splice(10..10, &[0xCC; 5]);
+        v.splice(10..10, vec![0xCC; 5]);
+        // A prefix of a block
+        let start = sv.len() - MAX_BLOCK_SIZE;
+        sv.splice(start..start + 3, &[]);
+        v.splice(start..start + 3, vec![]);
+
+        assert_eq!(sv.len(), v.len());
+        assert!(sv.copy_out(..) == v);
+    }
+
+    #[test]
+    fn test_mapped_find() {
+        let (mut sv, _) = create_test_mapped();
+
+        let marker = [0xDE, 0xAD, 0xBE, 0xEF];
+        let pos = 2*MAX_BLOCK_SIZE - 2;
+        sv.splice(pos..pos + marker.len(), &marker);
+
+        assert_eq!(Some(pos), sv.find_slice(&marker));
+        assert_eq!(None, sv.find_slice_from(pos + 1, &marker));
+    }
+
+    #[test]
+    fn test_mapped_rfind() {
+        let (mut sv, _) = create_test_mapped();
+
+        let marker = [0xDE, 0xAD, 0xBE, 0xEF];
+        let first = 10;
+        let pos = 2*MAX_BLOCK_SIZE - 2;
+        sv.splice(first..first + marker.len(), &marker);
+        sv.splice(pos..pos + marker.len(), &marker);
+
+        assert_eq!(Some(pos), sv.rfind_slice_from(sv.len(), &marker));
+        assert_eq!(Some(pos), sv.rfind_slice_from(pos + 1, &marker));
+        assert_eq!(Some(first), sv.rfind_slice_from(pos, &marker));
+        assert_eq!(None, sv.rfind_slice_from(first, &marker));
+    }
+
+    #[test]
+    fn test_masked_find() {
+        let (mut sv, _) = create_test_mapped();
+
+        let marker = [0xDE, 0xAD, 0x12, 0x34, 0xBE, 0xEF];
+        let pos = 2*MAX_BLOCK_SIZE - 3;
+        sv.splice(pos..pos + marker.len(), &marker);
+
+        let needle = [0xDE, 0xAD, 0x00, 0x30, 0xBE, 0xEF];
+        let mask = [0xFF, 0xFF, 0x00, 0xF0, 0xFF, 0xFF];
+        assert_eq!(Some(pos), sv.find_masked_from(0, &needle, &mask));
+        assert_eq!(None, sv.find_masked_from(pos + 1, &needle, &mask));
+        assert_eq!(None, sv.find_masked_from(0, &needle, &[0xFF; 6]));
+    }
 }
@@ -0,0 +1,184 @@
+//! Finding runs of printable characters in binary data, like the `strings` tool.
+
+/// The longest text kept for a string, longer strings are cut off with their length still counted
+pub const MAX_TEXT_LEN: usize = 80;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StringEncoding {
+    Ascii,
+    Utf16Le,
+}
+
+/// A run of printable characters in the data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundString {
+    pub offset: usize,
+    pub encoding: StringEncoding,
+    /// The length in characters
+    pub len: usize,
+    /// The start of the characters, at most `MAX_TEXT_LEN` of them
+    pub text: String,
+}
+
+fn is_printable(b: u8) -> bool {
+    (b >= 0x20 && b < 0x7f) || b == b'\t'
+}
+
+/// A run of characters that is still being collected
+struct Run {
+    offset: usize,
+    len: usize,
+    text: String,
+}
+
+impl Run {
+    fn new() -> Run {
+        Run {
+            offset: 0,
+            len: 0,
+            text: String::new(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.len > 0
+    }
+
+    fn push(&mut self, offset: usize, b: u8) {
+        if self.len == 0 {
+            self.offset = offset;
+            self.text.clear();
+        }
+        if self.len < MAX_TEXT_LEN {
+            self.text.push(b as char);
+        }
+        self.len += 1;
+    }
+
+    fn finish(&mut self, encoding: StringEncoding, min_len: usize, found: &mut Vec<FoundString>) {
+        if self.len >= min_len {
+            found.push(FoundString {
+                offset: self.offset,
+                encoding: encoding,
+                len: self.len,
+                text: self.text.clone(),
+            });
+        }
+        self.len = 0;
+    }
+}
+
+/// Find the strings of at least `min_len` characters in data given as consecutive slices, in
+/// ASCII and in UTF-16LE at any alignment. Keeps the first `max_count` strings and returns if
+/// there were more.
+pub fn find_strings<'a, I>(slices: I, min_len: usize, max_count: usize) -> (Vec<FoundString>, bool)
+        where I: Iterator<Item=&'a [u8]> {
+    let min_len = if min_len == 0 { 1 } else { min_len };
+    let mut found = vec![];
+    let mut ascii = Run::new();
+    // A UTF-16 run for code units at even and at odd offsets
+    let mut wide = [Run::new(), Run::new()];
+    let mut prev: Option<u8> = None;
+    let mut offset = 0;
+
+    'scan: for slice in slices {
+        for &b in slice {
+            if is_printable(b) {
+                ascii.push(offset, b);
+            } else {
+                ascii.finish(StringEncoding::Ascii, min_len, &mut found);
+            }
+
+            // Every byte completes a code unit that started at the previous byte
+            if let Some(p) = prev {
+                let unit_offset = offset - 1;
+                let run = &mut wide[unit_offset % 2];
+                if is_printable(p) && b == 0 {
+                    run.push(unit_offset, p);
+                } else {
+                    run.finish(StringEncoding::Utf16Le, min_len, &mut found);
+                }
+            }
+
+            prev = Some(b);
+            offset += 1;
+            // Strings that are still running started before any string found later, so they are
+            // finished before stopping
+            if found.len() > max_count && !ascii.is_open() && !wide.iter().any(Run::is_open) {
+                break 'scan;
+            }
+        }
+    }
+
+    // Runs that are still open at the end of the data
+    ascii.finish(StringEncoding::Ascii, min_len, &mut found);
+    for run in wide.iter_mut() {
+        run.finish(StringEncoding::Utf16Le, min_len, &mut found);
+    }
+
+    // Runs are found when they end, which isn't the order they start in
+    found.sort_by(|a, b| a.offset.cmp(&b.offset));
+    let truncated = found.len() > max_count;
+    found.truncate(max_count);
+    (found, truncated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(data: &[&[u8]], min_len: usize) -> Vec<(usize, StringEncoding, String)> {
+        find_strings(data.iter().cloned(), min_len, 100).0.into_iter().map(|s|
+            (s.offset, s.encoding, s.text)
+        ).collect()
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(strings(&[b"\x00abc\x01hello\x00", b"wor", b"ld"], 4), vec![
+            (5, StringEncoding::Ascii, "hello".to_string()),
+            (11, StringEncoding::Ascii, "world".to_string()),
+        ]);
+        assert_eq!(strings(&[b"abc"], 3), vec![(0, StringEncoding::Ascii, "abc".to_string())]);
+    }
+
+    #[test]
+    fn test_utf16() {
+        assert_eq!(strings(&[b"\x01h\x00i\x00", b"!\x00\x00\x00"], 3), vec![
+            (1, StringEncoding::Utf16Le, "hi!".to_string()),
+        ]);
+        assert_eq!(strings(&[b"a\x00b\x00c\x00"], 3), vec![
+            (0, StringEncoding::Utf16Le, "abc".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let long = vec![b'a'; MAX_TEXT_LEN + 10];
+        let (found, truncated) = find_strings(Some(&long[..]).into_iter(), 4, 10);
+        assert!(!truncated);
+        assert_eq!(found[0].len, MAX_TEXT_LEN + 10);
+        assert_eq!(found[0].text.len(), MAX_TEXT_LEN);
+
+        let many: Vec<u8> = (0..5).flat_map(|_| b"abcd\x00".iter().cloned()).collect();
+        let (found, truncated) = find_strings(Some(&many[..]).into_iter(), 4, 3);
+        assert!(truncated);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[2].offset, 10);
+
+        // Exactly as many strings as the limit, the last one still open at the end
+        let (found, truncated) = find_strings(Some(&many[..24]).into_iter(), 4, 5);
+        assert!(!truncated);
+        assert_eq!(found.len(), 5);
+
+        // The limit is reached by short ASCII strings while a long UTF-16 one is still running
+        let wide: Vec<u8> = b"abcdefgh".iter().flat_map(|&b| vec![b, 0]).collect();
+        let (found, truncated) = find_strings(Some(&wide[..]).into_iter(), 1, 2);
+        assert!(truncated);
+        let utf16: Vec<&FoundString> = found.iter()
+            .filter(|s| s.encoding == StringEncoding::Utf16Le).collect();
+        assert_eq!(utf16.len(), 1);
+        assert_eq!(utf16[0].len, 8);
+        assert_eq!(utf16[0].text, "abcdefgh");
+    }
+}
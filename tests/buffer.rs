@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate odds;
+
+extern crate rex;
+
+mod util;
+
+use std::path::Path;
+use std::iter;
+
+use odds::vec::VecExt;
+
+use rex::frontend::KeyPress;
+use rex::ui::view::HexEdit;
+
+use util::mock_filesystem::{ThreadedMockFilesystem, MockFilesystem};
+use util::mock_buffer::{MockBuffer, ReadOnlyMockBuffer};
+
+#[test]
+/// Test that the editor works over a buffer that only implements the required methods
+fn test_minimal_buffer() {
+    let mut v: Vec<u8> = iter::repeat(0).take(100).collect();
+    v.append(&mut vec![0x78, 0x78, 0x78, 0x78]);
+    v.append(&mut iter::repeat(0).take(100).collect());
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, MockBuffer>, _) =
+        util::simple_init_helper(Some(v));
+
+    // Find across the slices of the buffer
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('h')]);
+    frontend.run_str(&mut edit, "78787878");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 100);
+
+    // Edit over it
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert]);
+    frontend.run_str(&mut edit, "AABB");
+    result.splice(100..100, vec![0xAA, 0xBB]);
+
+    edit.save(Path::new("test_minimal_buffer"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_minimal_buffer").iter());
+}
+
+#[test]
+/// Test that a read-only buffer is never modified
+fn test_read_only_buffer() {
+    let v = util::generate_vec(100);
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, ReadOnlyMockBuffer>, _) =
+        util::simple_init_helper(Some(v.clone()));
+
+    frontend.run_str(&mut edit, "AABB");
+    frontend.run_keys(&mut edit, vec![KeyPress::Delete, KeyPress::Backspace]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' '), KeyPress::Right, KeyPress::Shortcut('x')]);
+    assert_eq!(edit.get_position(), 0);
+
+    edit.save(Path::new("test_read_only_buffer"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_read_only_buffer").iter());
+}
@@ -107,3 +107,25 @@ fn test_cut_paste() {
     edit.save(Path::new("test_cut_paste"));
     util::assert_iter_eq(v_copy.iter(), ThreadedMockFilesystem::get_inner("test_cut_paste").iter());
 }
+
+#[test]
+/// Test that a checksum can be copied and pasted
+fn test_copy_checksum() {
+    let v : Vec<u8> = b"123456789".to_vec();
+    let mut v_copy = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    // Copy the CRC32 of the whole buffer, the first checksum
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "h");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+
+    // And paste it in the end
+    frontend.run_keys(&mut edit, vec![KeyPress::PageDown, KeyPress::Shortcut('v')]);
+    let l = v_copy.len();
+    v_copy.splice(l.., vec![0xCB, 0xF4, 0x39, 0x26]);
+
+    edit.save(Path::new("test_copy_checksum"));
+    util::assert_iter_eq(v_copy.iter(), ThreadedMockFilesystem::get_inner("test_copy_checksum").iter());
+}
@@ -13,6 +13,8 @@ use std::io::{Write, Cursor, Seek, SeekFrom};
 use odds::vec::VecExt;
 
 use rex::frontend::{Event, KeyPress};
+use rex::ui::view::HexEdit;
+use rex::util::piece_table::PieceTable;
 
 use util::mock_filesystem::{ThreadedMockFilesystem, MockFilesystem};
 
@@ -123,3 +125,242 @@ fn test_edit_delete_and_bksp() {
     edit.save(Path::new("test_edit_delete_and_bksp"));
     util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_edit_delete_and_bksp").iter());
 }
+
+#[test]
+fn test_edit_piece_table() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, PieceTable>, _) =
+        util::simple_init_helper(Some(v));
+
+    // Overwrite in the begining
+    frontend.run_str(&mut edit, "AABB");
+    result.splice(0..2, vec![0xAA, 0xBB]);
+
+    // Insert some junk in the middle
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('g')]);
+    frontend.run_str(&mut edit, "50");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Insert]);
+    frontend.run_str(&mut edit, "CCDDEE");
+    result.splice(50..50, vec![0xCC, 0xDD, 0xEE]);
+
+    // And delete some
+    frontend.run_keys(&mut edit, vec![KeyPress::Delete, KeyPress::Delete]);
+    result.splice(53..55, vec![]);
+
+    edit.save(Path::new("test_edit_piece_table"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_edit_piece_table").iter());
+}
+
+#[test]
+fn test_write_value() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Write a little endian u32 and a big endian i16 after it
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('g')]);
+    frontend.run_str(&mut edit, "16");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('w'), KeyPress::Shortcut('d'), KeyPress::Shortcut('e')]);
+    frontend.run_str(&mut edit, "0x01020304");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    result[16..20].copy_from_slice(&[4, 3, 2, 1]);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 8]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('w'), KeyPress::Shortcut('w'), KeyPress::Shortcut('s')]);
+    frontend.run_str(&mut edit, "-2");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    result[20..22].copy_from_slice(&[0xff, 0xfe]);
+
+    edit.save(Path::new("test_write_value"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_write_value").iter());
+
+    // Each value is undone as a whole
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+    edit.save(Path::new("test_write_value"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_write_value").iter());
+}
+
+#[test]
+fn test_increment_decrement() {
+    {
+        let mut f = ThreadedMockFilesystem::save_config().unwrap();
+        f.write_all("group_bytes=2\nlittle_endian=true\n".as_bytes()).unwrap();
+    }
+    let v : Vec<u8> = vec![0xff, 0x00, 0x10, 0x00, 0x00, 0x00];
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Incrementing carries into the next byte of the group
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('a')]);
+    result[0..2].copy_from_slice(&[0x00, 0x01]);
+
+    // A count sets the step, and the cursor can be anywhere in the group
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 6]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "32");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('d')]);
+    result[2..4].copy_from_slice(&[0xf0, 0xff]);
+
+    edit.save(Path::new("test_increment_decrement"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_increment_decrement").iter());
+
+    // Each change is a single undo
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+    result[2..4].copy_from_slice(&[0x10, 0x00]);
+    edit.save(Path::new("test_increment_decrement"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_increment_decrement").iter());
+}
+
+#[test]
+fn test_transform_selection() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Select the first 4 bytes and XOR them with a repeating key
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' ')]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 6]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "tx");
+    frontend.run_str(&mut edit, "ff00");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    result[0..4].copy_from_slice(&[0xff, 0x01, 0xfd, 0x03]);
+
+    edit.save(Path::new("test_transform_selection"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_transform_selection").iter());
+
+    // NOT doesn't ask for anything, and is undone in one step
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "tn");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_transform_selection"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_transform_selection").iter());
+}
+
+#[test]
+fn test_fill() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Zero out the first 4 bytes
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' ')]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 6]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f00");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Shortcut(' ')]);
+    result[0..4].copy_from_slice(&[0, 0, 0, 0]);
+
+    // Insert 3 copies of a string
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert, KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "3");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('a')]);
+    frontend.run_str(&mut edit, "ab");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    let pos = edit.get_position() as usize;
+    result.splice(pos..pos, b"ababab".to_vec());
+
+    // A count that is too large is refused instead of running out of memory
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "99999999999999");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    assert_eq!(edit.get_last_status(), Some("Count too large"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "100000000");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('a')]);
+    frontend.run_str(&mut edit, "abcd");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_last_status(), Some("Count too large"));
+
+    edit.save(Path::new("test_fill"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_fill").iter());
+}
+
+#[test]
+fn test_fill_empty_selection() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // A selection at the end of the buffer has nothing in it
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('g')]);
+    frontend.run_str(&mut edit, "255");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Shortcut(' '), KeyPress::Insert]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    assert_eq!(edit.get_last_status(), Some("Nothing to fill, the selection is empty"));
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+    assert_eq!(edit.get_last_status(), Some("Nothing to undo"));
+
+    edit.save(Path::new("test_fill_empty_selection"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_fill_empty_selection").iter());
+}
+
+#[test]
+fn test_checksum_fixup() {
+    let v : Vec<u8> = (0..0x20).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Store the sum16 of the first 4 bytes at 0x10
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' ')]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 6]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "xd");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('n'), KeyPress::Shortcut('n'),
+        KeyPress::Shortcut('n')]);
+    frontend.run_str(&mut edit, "0x10");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Shortcut(' ')]);
+    result[0x10..0x12].copy_from_slice(&[0x00, 0x06]);
+
+    // Insert a byte before the data and change it, then apply the fix-up again
+    frontend.run_keys(&mut edit, vec![KeyPress::Left; 6]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert]);
+    frontend.run_str(&mut edit, "ff");
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert]);
+    frontend.run_str(&mut edit, "10");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "xa");
+    result[0] = 0x10;
+    result.splice(0..0, vec![0xff]);
+    result[0x11..0x13].copy_from_slice(&[0x00, 0x16]);
+
+    edit.save(Path::new("test_checksum_fixup"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_checksum_fixup").iter());
+}
+
+#[test]
+fn test_byte_swap() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Select 6 bytes, which can't be swapped as 32-bit words but can as 16-bit ones
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' ')]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Right; 10]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "tsd");
+    assert_eq!(edit.get_last_status(), Some("Selection size 6 isn't a multiple of the 4 byte word size"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "tsw");
+    result[0..6].copy_from_slice(&[1, 0, 3, 2, 5, 4]);
+
+    edit.save(Path::new("test_byte_swap"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_byte_swap").iter());
+}
@@ -57,3 +57,38 @@ fn test_basic_save() {
     // Make sure they are equal
     util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_basic_save").iter());
 }
+
+#[test]
+fn test_save_over_open() {
+    let v = util::generate_vec(1000);
+    let (mut edit, mut frontend) = util::simple_init_empty();
+    let pedit = &mut edit;
+
+    ThreadedMockFilesystem::put("test_save_over_open", v.clone());
+    pedit.open(Path::new("test_save_over_open"));
+
+    // Change the first byte and save back over the opened file
+    frontend.run_str(pedit, "FF");
+    pedit.save(Path::new("test_save_over_open"));
+
+    let mut result = v.clone();
+    result[0] = 0xFF;
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_save_over_open").iter());
+    // Make sure no temporary files were left behind
+    assert_eq!(ThreadedMockFilesystem::list_paths().len(), 0);
+}
+
+#[test]
+fn test_save_through_link() {
+    let v = util::generate_vec(1000);
+    let (mut edit, _) = util::simple_init_with_vec(v.clone());
+
+    ThreadedMockFilesystem::put("test_save_through_link_target", vec![0; 10]);
+    ThreadedMockFilesystem::put_link("test_save_through_link", "test_save_through_link_target");
+    edit.save(Path::new("test_save_through_link"));
+
+    // The file the link points to is replaced, and the link stays a link
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_save_through_link_target").iter());
+    assert_eq!(ThreadedMockFilesystem::list_paths().len(), 0);
+    assert_eq!(Path::new("test_save_through_link"), edit.get_file_path().unwrap());
+}
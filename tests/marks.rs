@@ -0,0 +1,142 @@
+#[macro_use]
+extern crate lazy_static;
+
+extern crate rex;
+
+mod util;
+
+use std::path::Path;
+
+use rex::frontend::KeyPress;
+use rex::ui::view::HexEdit;
+
+use util::mock_filesystem::{MockFilesystem, ThreadedMockFilesystem};
+use util::mock_frontend::MockFrontend;
+
+fn add_mark(edit: &mut HexEdit<MockFilesystem>, frontend: &mut MockFrontend, name: &str) {
+    frontend.run_keys(edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(edit, "ma");
+    frontend.run_str(edit, name);
+    frontend.run_keys(edit, vec![KeyPress::Enter]);
+}
+
+fn goto_mark(edit: &mut HexEdit<MockFilesystem>, frontend: &mut MockFrontend, index: usize) {
+    frontend.run_keys(edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(edit, "mg");
+    frontend.run_keys(edit, vec![KeyPress::Down; index]);
+    frontend.run_keys(edit, vec![KeyPress::Enter]);
+}
+
+#[test]
+fn test_mark_goto() {
+    let (mut edit, mut frontend) = util::simple_init(1000);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down; 3]);
+    let first_pos = edit.get_position();
+    add_mark(&mut edit, &mut frontend, "first");
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down; 5]);
+    let second_pos = edit.get_position();
+    add_mark(&mut edit, &mut frontend, "second");
+
+    frontend.run_keys(&mut edit, vec![KeyPress::PageDown]);
+
+    goto_mark(&mut edit, &mut frontend, 0);
+    assert_eq!(edit.get_position(), first_pos);
+
+    goto_mark(&mut edit, &mut frontend, 1);
+    assert_eq!(edit.get_position(), second_pos);
+}
+
+#[test]
+fn test_mark_shifts_with_edits() {
+    let (mut edit, mut frontend) = util::simple_init(1000);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down; 3]);
+    let mark_pos = edit.get_position();
+    add_mark(&mut edit, &mut frontend, "mark");
+
+    // Insert three bytes and delete two before the mark
+    frontend.run_keys(&mut edit, vec![KeyPress::Home, KeyPress::Up, KeyPress::Insert]);
+    frontend.run_str(&mut edit, "AABBCC");
+    frontend.run_keys(&mut edit, vec![KeyPress::Backspace; 2]);
+
+    goto_mark(&mut edit, &mut frontend, 0);
+    assert_eq!(edit.get_position(), mark_pos + 1);
+
+    // And undoing the edits moves it back
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+    goto_mark(&mut edit, &mut frontend, 0);
+    assert_eq!(edit.get_position(), mark_pos);
+}
+
+#[test]
+fn test_marks_sidecar() {
+    ThreadedMockFilesystem::put("test_marks_sidecar", util::generate_vec(1000));
+
+    {
+        let (mut edit, mut frontend) = util::simple_init_empty();
+        edit.open(Path::new("test_marks_sidecar"));
+
+        frontend.run_keys(&mut edit, vec![KeyPress::Down; 2]);
+        add_mark(&mut edit, &mut frontend, "header");
+
+        frontend.run_keys(&mut edit, vec![KeyPress::Right, KeyPress::Right]);
+        frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' '), KeyPress::Right, KeyPress::Right,
+            KeyPress::Right, KeyPress::Right, KeyPress::Right, KeyPress::Right]);
+        frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+        frontend.run_str(&mut edit, "mncrc");
+        frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+        frontend.run_str(&mut edit, "CRC32 of the payload");
+        frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+
+        edit.save(Path::new("test_marks_sidecar"));
+    }
+
+    let sidecar = String::from_utf8(ThreadedMockFilesystem::get_inner("test_marks_sidecar.rex")).unwrap();
+    assert!(sidecar.contains("name = \"header\""));
+    assert!(sidecar.contains("name = \"crc\""));
+    assert!(sidecar.contains("comment = \"CRC32 of the payload\""));
+    ThreadedMockFilesystem::put("test_marks_sidecar.rex", sidecar.into_bytes());
+
+    // Reopening the file loads the marks back, and saving it elsewhere saves them along
+    let (mut edit, mut frontend) = util::simple_init_empty();
+    edit.open(Path::new("test_marks_sidecar"));
+    goto_mark(&mut edit, &mut frontend, 0);
+    let mark_pos = edit.get_position();
+    assert!(mark_pos > 0);
+
+    edit.save(Path::new("test_marks_sidecar_copy"));
+    let sidecar = String::from_utf8(ThreadedMockFilesystem::get_inner("test_marks_sidecar_copy.rex")).unwrap();
+    assert!(sidecar.contains(&format!("pos = {}", mark_pos)));
+    assert!(sidecar.contains("comment = \"CRC32 of the payload\""));
+}
+
+#[test]
+fn test_marks_sidecar_control_chars() {
+    ThreadedMockFilesystem::put("test_marks_sidecar_control_chars", util::generate_vec(1000));
+
+    {
+        let (mut edit, mut frontend) = util::simple_init_empty();
+        edit.open(Path::new("test_marks_sidecar_control_chars"));
+
+        // A name with a control character in it is refused until it is removed
+        frontend.run_keys(&mut edit, vec![KeyPress::Shortcut(' '), KeyPress::Right, KeyPress::Right]);
+        frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+        frontend.run_str(&mut edit, "mncrc\x01");
+        frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Backspace, KeyPress::Enter]);
+        frontend.run_str(&mut edit, "CRC32\tof the payload");
+        frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+        assert_eq!(edit.get_last_status(), Some("Annotated 2 bytes as crc"));
+
+        edit.save(Path::new("test_marks_sidecar_control_chars"));
+    }
+
+    let (mut edit, _) = util::simple_init_empty();
+    edit.open(Path::new("test_marks_sidecar_control_chars"));
+    edit.save(Path::new("test_marks_sidecar_control_chars_copy"));
+    let sidecar = String::from_utf8(
+        ThreadedMockFilesystem::get_inner("test_marks_sidecar_control_chars_copy.rex")).unwrap();
+    assert!(sidecar.contains("name = \"crc\""));
+    assert!(sidecar.contains("comment = \"CRC32\\tof the payload\""));
+}
@@ -7,7 +7,7 @@ mod util;
 
 use std::iter;
 
-use rex::frontend::{Event, KeyPress};
+use rex::frontend::{Frontend, Event, KeyPress};
 
 #[test]
 /// Test that moving over the top works
@@ -127,3 +127,215 @@ fn test_find() {
     frontend.run_keys(pedit, vec![KeyPress::Enter]);
     assert_eq!(pedit.get_position(), 100);
 }
+
+#[test]
+/// Test finding hex with wildcards
+fn test_find_wildcards() {
+    let mut vec: Vec<u8> = iter::repeat(0).take(0x100).collect();
+    vec[0x10..0x16].copy_from_slice(&[0xde, 0xad, 0x12, 0x34, 0xbe, 0xee]);
+    vec[0x40..0x46].copy_from_slice(&[0xde, 0xad, 0x56, 0x78, 0xbe, 0xef]);
+    let (mut edit, mut frontend) = util::simple_init_with_vec(vec);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('h')]);
+    frontend.run_str(&mut edit, "DE AD ?? ?? BE EF");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x40);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('h')]);
+    frontend.run_str(&mut edit, "de ad 1? ?4");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x10);
+}
+
+#[test]
+/// Test finding a regex, and that an invalid one keeps the find line open
+fn test_find_regex() {
+    let mut vec: Vec<u8> = iter::repeat(0).take(0x100).collect();
+    vec[0x10..0x14].copy_from_slice(b"\x7fELF");
+    vec[0x40..0x44].copy_from_slice(b"\x7fELF");
+    vec[0x50..0x52].copy_from_slice(&[0x02, 0x00]);
+    let (mut edit, mut frontend) = util::simple_init_with_vec(vec);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('r')]);
+    frontend.run_str(&mut edit, "\\x7fELF.{12}\\x02(");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Backspace]);
+    frontend.run_str(&mut edit, "\\x00");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x40);
+}
+
+#[test]
+/// Test finding text in the unicode encodings
+fn test_find_unicode() {
+    let mut vec: Vec<u8> = iter::repeat(0).take(0x100).collect();
+    vec[0x10..0x16].copy_from_slice(b"a\0b\0c\0");
+    vec[0x40..0x46].copy_from_slice(b"\0a\0b\0c");
+    vec[0x80..0x8c].copy_from_slice(b"a\0\0\0b\0\0\0c\0\0\0");
+    vec[0xa0..0xa4].copy_from_slice(&[0x2d, 0x4e, 0xe9, 0x00]);
+    vec[0xc0..0xc5].copy_from_slice("中é".as_bytes());
+    let (mut edit, mut frontend) = util::simple_init_with_vec(vec);
+
+    // UTF-16LE
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "abc");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x10);
+
+    // UTF-16BE
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('u'),
+        KeyPress::Shortcut('e')]);
+    frontend.run_str(&mut edit, "abc");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x40);
+
+    // UTF-32LE
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('u'),
+        KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "abc");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x80);
+
+    // Non-ASCII UTF-16LE, editing whole characters
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "中xé");
+    frontend.run_keys(&mut edit, vec![KeyPress::Left, KeyPress::Backspace, KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0xa0);
+
+    // Non-ASCII UTF-8
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('u'),
+        KeyPress::Shortcut('u'), KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "中é");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0xc0);
+}
+
+#[test]
+fn test_find_value() {
+    let mut vec: Vec<u8> = iter::repeat(0).take(0x100).collect();
+    vec[0x20..0x24].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+    vec[0x50..0x54].copy_from_slice(&[0x78, 0x56, 0x34, 0x12]);
+    // 1.5001 as an f32 BE
+    vec[0x90..0x94].copy_from_slice(&[0x3f, 0xc0, 0x06, 0x8e]);
+    let (mut edit, mut frontend) = util::simple_init_with_vec(vec);
+
+    // u32 BE, the default byte order
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('n'),
+        KeyPress::Shortcut('d')]);
+    frontend.run_str(&mut edit, "0x12345678");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x20);
+
+    // u32 LE
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('n'),
+        KeyPress::Shortcut('d'), KeyPress::Shortcut('e')]);
+    frontend.run_str(&mut edit, "305419896");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x50);
+
+    // f32 BE within a tolerance
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f'), KeyPress::Shortcut('n'),
+        KeyPress::Shortcut('n'), KeyPress::Shortcut('n')]);
+    frontend.run_str(&mut edit, "1.5~0.001");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x90);
+}
+
+#[test]
+fn test_find_next_previous() {
+    let mut vec: Vec<u8> = iter::repeat(0).take(0x100).collect();
+    for &pos in &[0x10, 0x40, 0x80] {
+        vec[pos..pos + 3].copy_from_slice(b"abc");
+    }
+    let (mut edit, mut frontend) = util::simple_init_with_vec(vec);
+
+    // Nothing was searched for yet
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('n')]);
+    assert_eq!(edit.get_position(), 0);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('f')]);
+    frontend.run_str(&mut edit, "abc");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x10);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('n')]);
+    assert_eq!(edit.get_position(), 0x40);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('n')]);
+    assert_eq!(edit.get_position(), 0x80);
+    // Wraps around the end
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('n')]);
+    assert_eq!(edit.get_position(), 0x10);
+
+    // And backward around the start
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('b')]);
+    assert_eq!(edit.get_position(), 0x80);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('b')]);
+    assert_eq!(edit.get_position(), 0x40);
+}
+
+#[test]
+/// Test that the inspector panel narrows the rows of the view
+fn test_inspector_narrows_rows() {
+    let (mut edit, mut frontend) = util::simple_init(0x1000);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down]);
+    let full_row = edit.get_position();
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Up, KeyPress::Shortcut('e'), KeyPress::Down]);
+    let narrow_row = edit.get_position();
+    assert!(narrow_row > 0 && narrow_row < full_row);
+
+    // A screen too narrow for the panel doesn't show it
+    frontend.run_events(&mut edit, vec![Event::Resize(40, 100)]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Up, KeyPress::Down]);
+    let with_panel = edit.get_position();
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('e'), KeyPress::Up, KeyPress::Down]);
+    assert_eq!(edit.get_position(), with_panel);
+}
+
+#[test]
+/// Test jumping through the minimap to the block of a row
+fn test_minimap_jump() {
+    let (mut edit, mut frontend) = util::simple_init(0x1000);
+    // A width that has no room to spare for the minimap
+    frontend.run_events(&mut edit, vec![Event::Resize(97, 100)]);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down]);
+    let full_row = edit.get_position();
+
+    // The minimap takes some room from the rows
+    frontend.run_keys(&mut edit, vec![KeyPress::Up, KeyPress::Shortcut('p'), KeyPress::Down]);
+    let narrow_row = edit.get_position();
+    assert!(narrow_row > 0 && narrow_row < full_row);
+
+    // Jump to the block of the last row, which ends at the end of the buffer
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "pj");
+    let height = frontend.height() - 1;
+    frontend.run_keys(&mut edit, vec![KeyPress::Down; height]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position() as usize, 0x1000 * (height - 1) / height);
+}
+
+#[test]
+/// Test going to a string picked from the strings panel
+fn test_strings_goto() {
+    let mut v = vec![0; 0x100];
+    v[0x10..0x15].copy_from_slice(b"hello");
+    v[0x40..0x48].copy_from_slice(b"w\0o\0r\0d\0");
+    // Too short to be listed
+    v[0x80..0x82].copy_from_slice(b"no");
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "n");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x10);
+    assert_eq!(edit.get_last_status(), Some("Going to 16"));
+
+    // Moving down stops at the last string, the short one isn't listed
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "n");
+    frontend.run_keys(&mut edit, vec![KeyPress::Down, KeyPress::Down, KeyPress::Down, KeyPress::Enter]);
+    assert_eq!(edit.get_position(), 0x40);
+    assert_eq!(edit.get_last_status(), Some("Going to 64"));
+}
@@ -5,9 +5,12 @@ extern crate rex;
 
 mod util;
 
+use std::io::Write;
 use std::path::Path;
 
 use rex::frontend::{KeyPress};
+use rex::ui::view::HexEdit;
+use rex::util::piece_table::PieceTable;
 
 use util::mock_filesystem::{MockFilesystem, ThreadedMockFilesystem};
 
@@ -43,3 +46,242 @@ fn test_undo_delete() {
     edit.save(Path::new("test_undo_delete"));
     util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_undo_delete").iter());
 }
+
+#[test]
+fn test_undo_after_move() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Down, KeyPress::Down]);
+    frontend.run_str(&mut edit, "AABB");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z'),
+        KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_undo_after_move"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_undo_after_move").iter());
+}
+
+#[test]
+fn test_redo() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Right, KeyPress::Right]);
+    frontend.run_str(&mut edit, "AABB");
+    edit.save(Path::new("test_redo_expected"));
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z'),
+        KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('y'), KeyPress::Shortcut('y'),
+        KeyPress::Shortcut('y'), KeyPress::Shortcut('y')]);
+    assert_eq!(edit.get_position(), 3);
+
+    edit.save(Path::new("test_redo"));
+    util::assert_iter_eq(ThreadedMockFilesystem::get_inner("test_redo_expected").iter(),
+        ThreadedMockFilesystem::get_inner("test_redo").iter());
+}
+
+#[test]
+fn test_redo_cleared_by_edit() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+    result[0] = 0xBB;
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_str(&mut edit, "AA");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+    frontend.run_str(&mut edit, "BB");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('y')]);
+
+    edit.save(Path::new("test_redo_cleared_by_edit"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_redo_cleared_by_edit").iter());
+}
+
+#[test]
+fn test_undo_groups_typing() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let result = v.clone();
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert]);
+    frontend.run_str(&mut edit, "00112233445566778899AABBCCDDEEFF");
+    assert_eq!(edit.get_position(), 16);
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+    assert_eq!(edit.get_position(), 0);
+
+    edit.save(Path::new("test_undo_groups_typing"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_undo_groups_typing").iter());
+}
+
+#[test]
+fn test_undo_separates_groups() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+    result[0] = 0xAA;
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_str(&mut edit, "AA");
+    frontend.run_keys(&mut edit, vec![KeyPress::Down]);
+    frontend.run_str(&mut edit, "BBCC");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_undo_separates_groups"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_undo_separates_groups").iter());
+}
+
+#[test]
+fn test_undo_closes_earlier_group() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    let mut result = v.clone();
+    result[0] = 0xAA;
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v);
+
+    frontend.run_str(&mut edit, "AA");
+    frontend.run_keys(&mut edit, vec![KeyPress::Down]);
+    frontend.run_str(&mut edit, "BB");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Up]);
+    assert_eq!(edit.get_position(), 1);
+
+    // Typing next to the first edit after undoing the second one starts a new group
+    frontend.run_str(&mut edit, "CC");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_undo_closes_earlier_group"));
+    util::assert_iter_eq(result.iter(), ThreadedMockFilesystem::get_inner("test_undo_closes_earlier_group").iter());
+}
+
+#[test]
+fn test_undo_limits_redo_memory() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    {
+        let mut f = ThreadedMockFilesystem::save_config().unwrap();
+        f.write_all("undo_memory_limit=1\n".as_bytes()).unwrap();
+    }
+
+    let (mut edit, mut frontend) = util::simple_init_with_vec(v.clone());
+
+    // Undoing an insert of 2 MB keeps its bytes for redo, which goes over the limit
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert, KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "1048576");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    frontend.run_str(&mut edit, "abcd");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter, KeyPress::Shortcut('z')]);
+    assert_eq!(edit.get_last_status(), Some("Undo history over 1 MB, dropped 1 oldest edits"));
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('y')]);
+    assert_eq!(edit.get_last_status(), Some("Nothing to redo"));
+
+    edit.save(Path::new("test_undo_limits_redo_memory"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_undo_limits_redo_memory").iter());
+}
+
+#[test]
+fn test_undo_piece_table_keeps_pieces() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    {
+        let mut f = ThreadedMockFilesystem::save_config().unwrap();
+        f.write_all("undo_memory_limit=1\n".as_bytes()).unwrap();
+    }
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, PieceTable>, _) =
+        util::simple_init_helper(Some(v.clone()));
+
+    // The undone 2 MB insert is kept as pieces rather than copied, so it fits in the limit
+    frontend.run_keys(&mut edit, vec![KeyPress::Insert, KeyPress::Shortcut('u')]);
+    frontend.run_str(&mut edit, "1048576");
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('\\')]);
+    frontend.run_str(&mut edit, "f");
+    frontend.run_str(&mut edit, "abcd");
+    frontend.run_keys(&mut edit, vec![KeyPress::Enter]);
+    edit.save(Path::new("test_undo_piece_table_keeps_pieces_expected"));
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+    edit.save(Path::new("test_undo_piece_table_keeps_pieces"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_undo_piece_table_keeps_pieces").iter());
+
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('y')]);
+    assert_eq!(edit.get_last_status(), Some("Redid: fill 2097152 bytes"));
+    edit.save(Path::new("test_undo_piece_table_keeps_pieces"));
+    util::assert_iter_eq(ThreadedMockFilesystem::get_inner("test_undo_piece_table_keeps_pieces_expected").iter(),
+        ThreadedMockFilesystem::get_inner("test_undo_piece_table_keeps_pieces").iter());
+}
+
+fn enable_persistent_history() {
+    let mut f = ThreadedMockFilesystem::save_config().unwrap();
+    f.write_all("persist_history=true\n".as_bytes()).unwrap();
+}
+
+fn edit_and_save(path: &str, v: Vec<u8>) {
+    ThreadedMockFilesystem::put(path, v);
+
+    let (mut edit, mut frontend) = util::simple_init_empty();
+    edit.open(Path::new(path));
+    frontend.run_str(&mut edit, "AABB");
+    edit.save(Path::new(path));
+}
+
+#[test]
+fn test_persistent_history() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    enable_persistent_history();
+    edit_and_save("test_persistent_history", v.clone());
+
+    let (mut edit, mut frontend) = util::simple_init_empty();
+    edit.open(Path::new("test_persistent_history"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_persistent_history"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_persistent_history").iter());
+}
+
+#[test]
+fn test_persistent_history_changed_file() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    enable_persistent_history();
+    edit_and_save("test_persistent_history_changed_file", v.clone());
+
+    // Someone else changed the file since
+    let mut changed = v.clone();
+    changed[0] = 0x55;
+    changed[1] = 0x66;
+    ThreadedMockFilesystem::put("test_persistent_history_changed_file", changed.clone());
+
+    let (mut edit, mut frontend) = util::simple_init_empty();
+    edit.open(Path::new("test_persistent_history_changed_file"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Right, KeyPress::Right]);
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_persistent_history_changed_file"));
+    util::assert_iter_eq(changed.iter(), ThreadedMockFilesystem::get_inner("test_persistent_history_changed_file").iter());
+}
+
+#[test]
+fn test_persistent_history_piece_table() {
+    let v : Vec<u8> = (0..0xff).into_iter().collect();
+    enable_persistent_history();
+    ThreadedMockFilesystem::put("test_persistent_history_piece_table", v.clone());
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, PieceTable>, _) =
+        util::simple_init_helper(None);
+    edit.open(Path::new("test_persistent_history_piece_table"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Delete, KeyPress::Delete]);
+    frontend.run_str(&mut edit, "AABB");
+    edit.save(Path::new("test_persistent_history_piece_table"));
+
+    let (mut edit, mut frontend): (HexEdit<ThreadedMockFilesystem, PieceTable>, _) =
+        util::simple_init_helper(None);
+    edit.open(Path::new("test_persistent_history_piece_table"));
+    frontend.run_keys(&mut edit, vec![KeyPress::Shortcut('z'), KeyPress::Shortcut('z')]);
+
+    edit.save(Path::new("test_persistent_history_piece_table"));
+    util::assert_iter_eq(v.iter(), ThreadedMockFilesystem::get_inner("test_persistent_history_piece_table").iter());
+}
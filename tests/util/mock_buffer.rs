@@ -0,0 +1,115 @@
+use std::ops;
+use std::ops::Range;
+
+use rex::util::buffer::Buffer;
+
+/// A buffer implementing only the basic operations of Buffer over a single Vec, to make sure the
+/// editor works over the trait's default implementations.
+pub struct MockBuffer(Vec<u8>);
+
+impl Buffer for MockBuffer {
+    type Saved = Vec<u8>;
+
+    fn new() -> MockBuffer {
+        MockBuffer(Vec::new())
+    }
+
+    fn from_vec(values: Vec<u8>) -> MockBuffer {
+        MockBuffer(values)
+    }
+
+    fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> MockBuffer {
+        MockBuffer(source.as_ref().to_vec())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter_slices<'a>(&'a self) -> Box<Iterator<Item=&'a [u8]> + 'a> {
+        // Split the data up, so that operations crossing slices get tested
+        Box::new(self.0.chunks(7))
+    }
+
+    fn splice(&mut self, range: Range<usize>, values: &[u8]) -> Vec<u8> {
+        let end = if range.end > self.0.len() { self.0.len() } else { range.end };
+        let tail = self.0.split_off(end);
+        let res = self.0.split_off(range.start);
+        self.0.extend_from_slice(values);
+        self.0.extend(tail);
+        res
+    }
+
+    fn save(&mut self, values: Vec<u8>) -> Vec<u8> {
+        values
+    }
+
+    fn splice_saved(&mut self, range: Range<usize>, values: &Vec<u8>) -> Vec<u8> {
+        self.splice(range, values)
+    }
+
+    fn copy_saved(&self, values: &Vec<u8>) -> Vec<u8> {
+        values.clone()
+    }
+}
+
+impl ops::Index<usize> for MockBuffer {
+    type Output = u8;
+    fn index(&self, index: usize) -> &u8 {
+        &self.0[index]
+    }
+}
+
+/// A MockBuffer that does not allow modifications.
+pub struct ReadOnlyMockBuffer(MockBuffer);
+
+impl Buffer for ReadOnlyMockBuffer {
+    type Saved = Vec<u8>;
+
+    fn new() -> ReadOnlyMockBuffer {
+        ReadOnlyMockBuffer(MockBuffer::new())
+    }
+
+    fn from_vec(values: Vec<u8>) -> ReadOnlyMockBuffer {
+        ReadOnlyMockBuffer(MockBuffer::from_vec(values))
+    }
+
+    fn from_mapped<M: AsRef<[u8]> + 'static>(source: M) -> ReadOnlyMockBuffer {
+        ReadOnlyMockBuffer(MockBuffer::from_mapped(source))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn iter_slices<'a>(&'a self) -> Box<Iterator<Item=&'a [u8]> + 'a> {
+        self.0.iter_slices()
+    }
+
+    fn splice(&mut self, _range: Range<usize>, _values: &[u8]) -> Vec<u8> {
+        panic!("Modifying a read-only buffer!");
+    }
+
+    fn save(&mut self, values: Vec<u8>) -> Vec<u8> {
+        values
+    }
+
+    fn splice_saved(&mut self, _range: Range<usize>, _values: &Vec<u8>) -> Vec<u8> {
+        panic!("Modifying a read-only buffer!");
+    }
+
+    fn copy_saved(&self, values: &Vec<u8>) -> Vec<u8> {
+        values.clone()
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+impl ops::Index<usize> for ReadOnlyMockBuffer {
+    type Output = u8;
+    fn index(&self, index: usize) -> &u8 {
+        &self.0[index]
+    }
+}
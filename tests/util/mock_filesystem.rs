@@ -11,6 +11,7 @@ use std::marker::PhantomData;
 use rex::filesystem::Filesystem;
 
 const CONFIG_PATH: &'static str = "/config/rex/rex.conf";
+const DATA_PATH: &'static str = "/data";
 
 
 pub struct MockFile(Arc<Mutex<Vec<u8>>>, u64);
@@ -67,12 +68,15 @@ pub trait MockFilesystemBackend {
 
 struct MockFilesystemImpl {
     files: Arc<Mutex<HashMap<PathBuf, Arc<Mutex<Vec<u8>>>>>>,
+    /// Symbolic links, from the path of the link to the path it points to
+    links: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
 }
 
 impl Default for MockFilesystemImpl {
     fn default() -> MockFilesystemImpl {
         MockFilesystemImpl {
-            files: Arc::new(Mutex::new(HashMap::new()))
+            files: Arc::new(Mutex::new(HashMap::new())),
+            links: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
@@ -80,7 +84,8 @@ impl Default for MockFilesystemImpl {
 impl Clone for MockFilesystemImpl {
     fn clone(&self) -> MockFilesystemImpl {
         MockFilesystemImpl {
-            files: self.files.clone()
+            files: self.files.clone(),
+            links: self.links.clone(),
         }
     }
 }
@@ -125,6 +130,7 @@ impl MockFilesystemBackend for GlobalMockFilesystemBackend {
 impl<T: MockFilesystemBackend + 'static> Filesystem for MockFilesystem<T> {
     type FSRead = MockFile;
     type FSWrite = MockFile;
+    type FSMap = Vec<u8>;
 
     fn open_config<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, config_name: P2) -> Option<PathBuf> {
         Some(PathBuf::from(CONFIG_PATH))
@@ -134,6 +140,21 @@ impl<T: MockFilesystemBackend + 'static> Filesystem for MockFilesystem<T> {
         Ok(PathBuf::from(CONFIG_PATH))
     }
 
+    fn open_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> Option<PathBuf> {
+        let path = PathBuf::from(DATA_PATH).join(prefix).join(data_name);
+        let backend = T::get_backend();
+        let file_map = backend.files.lock().unwrap();
+        if file_map.contains_key(&path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    fn save_data<P1: AsRef<Path>, P2: AsRef<Path>>(prefix: P1, data_name: P2) -> io::Result<PathBuf> {
+        Ok(PathBuf::from(DATA_PATH).join(prefix).join(data_name))
+    }
+
     fn make_absolute<P: AsRef<Path>>(p: P) -> io::Result<PathBuf> {
         Ok(p.as_ref().into())
     }
@@ -146,6 +167,14 @@ impl<T: MockFilesystemBackend + 'static> Filesystem for MockFilesystem<T> {
         )
     }
 
+    fn open_mapped<P: AsRef<Path>>(path: P) -> io::Result<Self::FSMap> {
+        let backend = T::get_backend();
+        let file_map = backend.files.lock().unwrap();
+        file_map.get(path.as_ref()).ok_or(io::Error::new(io::ErrorKind::NotFound, "File not found!")).map(|file|
+            file.lock().unwrap().clone()
+        )
+    }
+
     fn can_open<P: AsRef<Path>>(_p: P) -> io::Result<()> {
         Ok(())
     }
@@ -161,6 +190,34 @@ impl<T: MockFilesystemBackend + 'static> Filesystem for MockFilesystem<T> {
     fn can_save<P: AsRef<Path>>(_p: P) -> io::Result<()> {
         Ok(())
     }
+
+    fn rename<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, to: P2) -> io::Result<()> {
+        let backend = T::get_backend();
+        let mut file_map = backend.files.lock().unwrap();
+        let file = try!(file_map.remove(from.as_ref()).ok_or(io::Error::new(io::ErrorKind::NotFound, "File not found!")));
+        file_map.insert(to.as_ref().into(), file);
+        Ok(())
+    }
+
+    fn remove<P: AsRef<Path>>(path: P) -> io::Result<()> {
+        let backend = T::get_backend();
+        let mut file_map = backend.files.lock().unwrap();
+        file_map.remove(path.as_ref()).map(|_| ()).ok_or(io::Error::new(io::ErrorKind::NotFound, "File not found!"))
+    }
+
+    fn resolve_links<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+        let backend = T::get_backend();
+        let links = backend.links.lock().unwrap();
+        let mut path: PathBuf = path.as_ref().into();
+        while let Some(target) = links.get(&path) {
+            path = target.clone();
+        }
+        Ok(path)
+    }
+
+    fn copy_permissions<P1: AsRef<Path>, P2: AsRef<Path>>(from: P1, _to: P2) -> io::Result<()> {
+        Self::open(from).map(|_| ())
+    }
 }
 
 impl<T: MockFilesystemBackend + 'static> MockFilesystem<T> {
@@ -195,6 +252,11 @@ impl<T: MockFilesystemBackend + 'static> MockFilesystem<T> {
         v
     }
 
+    pub fn put_link<P1: AsRef<Path>, P2: AsRef<Path>>(link: P1, target: P2) {
+        let backend = T::get_backend();
+        backend.links.lock().unwrap().insert(link.as_ref().into(), target.as_ref().into());
+    }
+
     pub fn put<'a, P: AsRef<Path>>(path: P, v: Vec<u8>) {
         let backend = T::get_backend();
         let mut file_map = backend.files.lock().unwrap();
@@ -1,10 +1,12 @@
 pub mod mock_frontend;
 pub mod mock_filesystem;
+pub mod mock_buffer;
 pub mod bytes;
 
 use std::fmt::Debug;
 
 use rex::ui::view::HexEdit;
+use rex::util::buffer::Buffer;
 
 // Little helper function till Iterator.eq stabalizes
 pub fn assert_iter_eq<I, J>(one: I, other: J) where
@@ -45,9 +47,9 @@ pub fn simple_init_with_vec(vec: Vec<u8>) -> (HexEdit<mock_filesystem::MockFiles
     simple_init_helper(Some(vec))
 }
 
-pub fn simple_init_helper<T: mock_filesystem::MockFilesystemBackend + 'static>(maybe_vec: Option<Vec<u8>>) ->
-        (HexEdit<mock_filesystem::MockFilesystem<T>>, mock_frontend::MockFrontend) {
-    let mut edit: HexEdit<mock_filesystem::MockFilesystem<T>> = HexEdit::new();
+pub fn simple_init_helper<T: mock_filesystem::MockFilesystemBackend + 'static, B: Buffer + 'static>(maybe_vec: Option<Vec<u8>>) ->
+        (HexEdit<mock_filesystem::MockFilesystem<T>, B>, mock_frontend::MockFrontend) {
+    let mut edit: HexEdit<mock_filesystem::MockFilesystem<T>, B> = HexEdit::new();
     let mut frontend = mock_frontend::MockFrontend::new();
 
     if let Some(vec) = maybe_vec {